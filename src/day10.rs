@@ -1,76 +1,289 @@
 use std::fs;
 
-fn find_valid_start_points(map: &Vec<Vec<char>>, cursor: (usize, usize)) -> Vec<(usize, usize)> {
-    // Get all valid starting directions.
-
-    let mut agenda = vec![];
+#[derive(Clone, Copy, PartialEq)]
+enum Heading {
+    North,
+    East,
+    South,
+    West
+}
 
-    // Left.
-    if cursor.0 > 0 && ['-', 'L', 'F'].contains(&map[cursor.1][cursor.0 - 1]) {
-        agenda.push((cursor.0 - 1, cursor.1));
+impl Heading {
+    fn opposite(&self) -> Heading {
+        return match self {
+            Heading::North => Heading::South,
+            Heading::East => Heading::West,
+            Heading::South => Heading::North,
+            Heading::West => Heading::East
+        };
     }
+}
+
+#[derive(Clone, Copy)]
+enum Method {
+    Shoelace,
+    Scanline
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Tile {
+    Wall,
+    Inside,
+    Outside
+}
+
+struct PipeLoop {
+    // Every tile of the loop in walking order, beginning at the start tile.
+    tiles: Vec<(usize, usize)>,
+    // The pipe the start tile acts as, which matters when it's 'S'.
+    start_shape: char,
+    clockwise: bool
+}
+
+fn openings(pipe: char) -> Vec<Heading> {
+    // The directions a pipe connects to.
+
+    return match pipe {
+        '|' => vec![Heading::North, Heading::South],
+        '-' => vec![Heading::East, Heading::West],
+        'L' => vec![Heading::North, Heading::East],
+        'J' => vec![Heading::North, Heading::West],
+        '7' => vec![Heading::South, Heading::West],
+        'F' => vec![Heading::South, Heading::East],
+        _ => vec![]
+    };
+}
+
+fn shape(a: Heading, b: Heading) -> char {
+    // The pipe connecting two directions, the inverse of `openings`.
 
-    // Top.
-    if cursor.1 > 0 && ['|', '7', 'F'].contains(&map[cursor.1 - 1][cursor.0]) {
-        agenda.push((cursor.0, cursor.1 - 1));
+    return ['|', '-', 'L', 'J', '7', 'F']
+        .into_iter()
+        .find(|pipe| {
+            let pipe_openings = openings(*pipe);
+            a != b && pipe_openings.contains(&a) && pipe_openings.contains(&b)
+        })
+        .unwrap_or('.');
+}
+
+fn step(map: &[Vec<char>], cursor: (usize, usize), heading: Heading) -> Option<(usize, usize)> {
+    // Move one tile, as long as that stays on the map.
+
+    let next = match heading {
+        Heading::North => (cursor.0, cursor.1.checked_sub(1)?),
+        Heading::East => (cursor.0 + 1, cursor.1),
+        Heading::South => (cursor.0, cursor.1 + 1),
+        Heading::West => (cursor.0.checked_sub(1)?, cursor.1)
+    };
+
+    map.get(next.1)?.get(next.0)?;
+
+    return Some(next);
+}
+
+fn find_valid_start_points(map: &[Vec<char>], cursor: (usize, usize)) -> Vec<Heading> {
+    // Get all valid starting directions: those whose neighbour has a pipe pointing back.
+
+    return [Heading::North, Heading::East, Heading::South, Heading::West]
+        .into_iter()
+        .filter(|heading| {
+            step(map, cursor, *heading)
+                .is_some_and(|(x, y)| openings(map[y][x]).contains(&heading.opposite()))
+        }).collect();
+}
+
+fn signed_area(tiles: &[(usize, usize)]) -> isize {
+    // Twice the signed area of the loop, by the shoelace formula.
+    // With y pointing down the map, this is positive for a clockwise loop.
+
+    return tiles
+        .iter()
+        .zip(tiles.iter().cycle().skip(1))
+        .map(|(a, b)| a.0 as isize * b.1 as isize - b.0 as isize * a.1 as isize)
+        .sum();
+}
+
+fn walk(
+    map: &[Vec<char>],
+    start: (usize, usize),
+    mut heading: Heading,
+    s_openings: &[Heading],
+    stop: impl Fn((usize, usize)) -> bool
+) -> (Vec<(usize, usize)>, Option<Heading>) {
+    // Follow the pipes from `start` until arriving back there, giving up early on any tile
+    // `stop` rejects.
+    // Returns the tiles walked, along with the heading we arrived back in if we did.
+    // Every pipe has one way in and one way out, so no record of visited tiles is needed.
+    // That includes 'S', which is passed through as the pipe it resolves to, `s_openings`.
+
+    let mut tiles = vec![start];
+    let mut cursor = start;
+
+    loop {
+        cursor = match step(map, cursor, heading) {
+            Some(next) if next == start => return (tiles, Some(heading)),
+            Some(next) if !stop(next) => next,
+            _ => return (tiles, None)
+        };
+
+        // The pipe we enter has to connect back to where we came from.
+        let pipe_openings = match map[cursor.1][cursor.0] {
+            'S' => s_openings.to_vec(),
+            pipe => openings(pipe)
+        };
+
+        if !pipe_openings.contains(&heading.opposite()) {
+            return (tiles, None);
+        }
+
+        heading = *pipe_openings.iter().find(|h| **h != heading.opposite()).unwrap();
+        tiles.push(cursor);
     }
+}
+
+fn resolve_s(map: &[Vec<char>]) -> Vec<Heading> {
+    // The openings of the pipe 'S' stands in for, from the loop through it.
+    // Without a loop through 'S', nothing can pass through it.
+
+    return get_start(map)
+        .and_then(|start| get_loop(map, start))
+        .map_or(vec![], |pipe_loop| openings(pipe_loop.start_shape));
+}
 
-    // Right.
-    if cursor.0 <= map[0].len() && ['-', '7', 'J'].contains(&map[cursor.1][cursor.0 + 1]) {
-        agenda.push((cursor.0 + 1, cursor.1));
+fn get_loop(map: &[Vec<char>], start: (usize, usize)) -> Option<PipeLoop> {
+    // Try leaving the start in each direction that connects to it, until one comes back round
+    // into another of those directions. The pair of directions gives the pipe 'S' stands in for.
+    // Any other start tile can only leave through its own openings, and passes through 'S' as
+    // the pipe it resolves to.
+
+    let pipe = map.get(start.1)?.get(start.0)?;
+    let s_openings = if *pipe == 'S' { vec![] } else { resolve_s(map) };
+    let headings = find_valid_start_points(map, start)
+        .into_iter()
+        .filter(|heading| *pipe == 'S' || openings(*pipe).contains(heading))
+        .collect::<Vec<Heading>>();
+
+    for heading in headings.iter() {
+        if let (tiles, Some(arrival)) = walk(map, start, *heading, &s_openings, |_| false) {
+            if headings.contains(&arrival.opposite()) && arrival.opposite() != *heading {
+                let clockwise = signed_area(&tiles) > 0;
+
+                return Some(PipeLoop {
+                    tiles,
+                    start_shape: shape(*heading, arrival.opposite()),
+                    clockwise
+                });
+            }
+        }
     }
 
-    // Bottom.
-    if cursor.1 <= map.len() && ['|', 'L', 'J'].contains(&map[cursor.1 + 1][cursor.0]) {
-        agenda.push((cursor.0, cursor.1 + 1));
+    return None;
+}
+
+fn find_loops(map: &[Vec<char>]) -> Vec<PipeLoop> {
+    // Find every closed loop of pipes in the map.
+    //
+    // Each pipe is walked at most once: a walk which fails to close can't pass through any loop,
+    // so its tiles are marked as seen along with those of any loop found, and later walks stop as
+    // soon as they reach a seen tile.
+
+    let mut seen = map.iter().map(|line| vec![false; line.len()]).collect::<Vec<Vec<bool>>>();
+    let mut loops = vec![];
+    let s_openings = resolve_s(map);
+
+    for y in 0..map.len() {
+        for x in 0..map[y].len() {
+            if seen[y][x] {
+                continue;
+            }
+
+            let pipe_openings = openings(map[y][x]);
+
+            let (tiles, found) = if map[y][x] == 'S' {
+                match get_loop(map, (x, y)) {
+                    Some(pipe_loop) => (pipe_loop.tiles.clone(), Some(pipe_loop)),
+                    None => (vec![(x, y)], None)
+                }
+            } else if let [first, second] = pipe_openings[..] {
+                let (tiles, arrival) = walk(map, (x, y), first, &s_openings, |(x, y)| seen[y][x]);
+
+                if arrival.is_some_and(|arrival| arrival.opposite() == second) {
+                    let clockwise = signed_area(&tiles) > 0;
+                    (tiles.clone(), Some(PipeLoop { tiles, start_shape: map[y][x], clockwise }))
+                } else {
+                    (tiles, None)
+                }
+            } else {
+                (vec![(x, y)], None)
+            };
+
+            tiles.iter().for_each(|(x, y)| seen[*y][*x] = true);
+            loops.extend(found);
+        }
     }
 
-    return agenda;
+    return loops;
 }
 
-fn get_loop(
-    map: &Vec<Vec<char>>,
-    mut cursor: (usize, usize)) -> Vec<(usize, usize)>
-{
-    // Keep track of everything we've seen so far.
-    let mut visited: Vec<(usize, usize)> = vec![];
+fn main_loop(map: &[Vec<char>], start: Option<(usize, usize)>) -> PipeLoop {
+    // The loop through the given start, or through 'S'.
+    // Without either, fall back to the longest loop in the map.
+
+    match start.or_else(|| get_start(map)) {
+        Some(start) => match get_loop(map, start) {
+            Some(pipe_loop) => pipe_loop,
+            None => panic!("No loop through {},{}", start.0, start.1)
+        },
+        None => match find_loops(map).into_iter().max_by_key(|pipe_loop| pipe_loop.tiles.len()) {
+            Some(pipe_loop) => pipe_loop,
+            None => panic!("No loop found")
+        }
+    }
+}
 
-    // We keep finding the next element until visited causes us to break.
-    loop {
-        visited.push(cursor);
-
-        let next = match map[cursor.1][cursor.0] {
-            'S' => find_valid_start_points(map, cursor),
-            other => {
-                let possible_directions: Vec<(usize, usize)> = match other {
-                    '-' => vec![(cursor.0 - 1, cursor.1), (cursor.0 + 1, cursor.1)],
-                    '|' => vec![(cursor.0, cursor.1 - 1), (cursor.0, cursor.1 + 1)],
-                    'J' => vec![(cursor.0, cursor.1 - 1), (cursor.0 - 1, cursor.1)],
-                    'F' => vec![(cursor.0 + 1, cursor.1), (cursor.0, cursor.1 + 1)],
-                    'L' => vec![(cursor.0, cursor.1 - 1), (cursor.0 + 1, cursor.1)],
-                    '7' => vec![(cursor.0 - 1, cursor.1), (cursor.0, cursor.1 + 1)],
-                    other => panic!("Bad input: {other}")
-                };
-
-                possible_directions
-                    .into_iter()
-                    .filter(|cur| !visited.contains(cur))
-                    .collect()
-            }
-        };
+fn loop_shapes(map: &[Vec<char>], pipe_loop: &PipeLoop) -> Vec<Vec<Option<char>>> {
+    // The pipe each loop tile acts as, worked out from its neighbours along the loop.
+    // This resolves 'S' wherever it falls in the loop.
+
+    let mut shapes = map.iter().map(|line| vec![None; line.len()]).collect::<Vec<Vec<Option<char>>>>();
+    let tiles = &pipe_loop.tiles;
 
-        // If there is no next element, that means we've found 'S', because 'S' is the first part
-        // of the loop inside visited. This means we can return the loop.
-        if next.len() == 0 {
-            return visited;
+    let heading = |from: (usize, usize), to: (usize, usize)| {
+        if to.0 > from.0 {
+            Heading::East
+        } else if to.0 < from.0 {
+            Heading::West
+        } else if to.1 > from.1 {
+            Heading::South
         } else {
-            // If the loop hasn't been found, there should be exactly one element in next.
-            cursor = *next.iter().nth(0).unwrap();
+            Heading::North
         }
     };
+
+    tiles.iter().enumerate().for_each(|(idx, tile)| {
+        let previous = tiles[(idx + tiles.len() - 1) % tiles.len()];
+        let next = tiles[(idx + 1) % tiles.len()];
+
+        shapes[tile.1][tile.0] = Some(shape(heading(*tile, previous), heading(*tile, next)));
+    });
+
+    return shapes;
+}
+
+fn get_map(path: &str) -> Vec<Vec<char>> {
+    // Parse pipe map.
+
+    if let Some(pipes) = fs::read_to_string(path).ok() {
+        return pipes
+            .lines()
+            .map(|line| line.chars().collect())
+            .collect();
+    }
+
+    panic!("file not found: {path}")
 }
 
-fn get_start(map: &Vec<Vec<char>>) -> Option<(usize, usize)> {
+fn get_start(map: &[Vec<char>]) -> Option<(usize, usize)> {
     // Find 'S' in the map
     for (y, line) in map.iter().enumerate() {
         for (x, c) in line.iter().enumerate() {
@@ -83,83 +296,44 @@ fn get_start(map: &Vec<Vec<char>>) -> Option<(usize, usize)> {
     return None;
 }
 
-fn loop_size() -> usize {
+fn loop_size(start: Option<(usize, usize)>) -> usize {
     // Find the biggest loop of pipes which contains 'S'.
     // We are given some helpful invariants:
     //   - S is only part of one loop,
     //   - There are only two pipes which point into S.
-    // This means we can find S, follow one of the pipes which points into it, keep track of which
-    // pipes we've seen, and exit once we find a pipe we've seen before (which will be S).
-
-    if let Some(pipes) = fs::read_to_string("data/10.input").ok() {
-        // Parse pipe map.
-        let map: Vec<Vec<char>> = pipes
-            .lines()
-            .map(|line| line.chars().collect())
-            .collect();
-
-        // Get starting point.
-        if let Some(start) = get_start(&map) {
-            // The problem actually wants the furthest we get from S, which is length / 2.
-            return get_loop(&map, start).len() / 2;
-        } else {
-            panic!("No 'S' starting point found")
-        }
+    // This means we can find S, follow one of the pipes which points into it, and exit once we
+    // arrive back at S.
 
-    }
+    let map = get_map("data/10.input");
 
-    panic!("file not found")
+    // The problem actually wants the furthest we get from S, which is length / 2.
+    return main_loop(&map, start).tiles.len() / 2;
 }
 
-fn remove_s(map: &Vec<Vec<char>>, char: char, location: (usize, usize)) -> char {
-    // Convert S into the character it acts as.
-    // This is hairy manual logic.
-
-    if char != 'S' {
-        return char;
-    }
-
-    // Check whether S connects to each cardinal direction.
-    let left  = location.0 > 0 && ['-', 'F', 'L'].contains(&map[location.0 - 1][location.1]);
-    let top   = location.1 > 0 && ['|', 'F', '7'].contains(&map[location.0][location.1 - 1]);
-    let right = location.0 <= map[0].len() && ['-', 'J', '7'].contains(&map[location.0 + 1][location.1]);
-    let down  = location.1 <= map.len() && ['|', 'L', 'J'].contains(&map[location.0][location.1 + 1]);
-
-    // Match the possible combinations.
-    return match (left, top, right, down) {
-        (_, true, _, true) => '|',
-        (true, _, true, _) => '-',
-        (_, _, true, true) => 'F',
-        (true, true, _, _) => 'J',
-        (true, _, _, true) => '7',
-        (_, true, true, _) => 'L',
-
-        // S is guaranteed by the input to contain exactly two connections.
-        _ => panic!("Bad value for S")
-    };
-}
-
-fn enclosed_tiles(map: &Vec<Vec<char>>, walls: Vec<(usize, usize)>) -> usize {
-    // Count enclosed tiles.
+fn classify_tiles(map: &[Vec<char>], pipe_loop: &PipeLoop) -> Vec<Vec<Tile>> {
+    // Mark every tile as part of the loop, inside it, or outside it.
     // This involves quite involved logic.
     //   - At any point in time we track whether we're inside or outside.
-    //   - If a tile isn't part of walls, and we're inside, increment a counter.
+    //   - If a tile isn't part of walls, mark it as inside or outside.
     //   - If a tile is part of walls, modify the inside/outside tracker:
-    //      Wall is 'S' => find out what 'S' emulates, then act accordingly.
+    //      Wall is 'S' => act as the pipe it emulates.
     //      Wall is '|' => flip state.
     //      Wall is 'F'/'L' => flip state, but set the "unflipper" to '7'/'J' respectively.
     //      Wall is '7'/'J' => if is set as the flipper, flip state. Otherwise do nothing.
     //      Wall is '-' => do nothing.
 
-    let mut count = 0;
+    let mut tiles = map.iter().map(|line| vec![Tile::Outside; line.len()]).collect::<Vec<Vec<Tile>>>();
+    let shapes = loop_shapes(map, pipe_loop);
 
-    for (y, line) in map.iter().enumerate() {
+    for (y, line) in shapes.iter().enumerate() {
         let mut enclosed = false;
         let mut unflip = '.';
 
-        for (x, c) in line.iter().enumerate() {
-            if walls.contains(&(x, y)) {
-                match remove_s(map, *c, (x, y)) {
+        for (x, shape) in line.iter().enumerate() {
+            if let Some(pipe) = shape {
+                tiles[y][x] = Tile::Wall;
+
+                match *pipe {
                     '|' => enclosed = !enclosed,
                     'F' => {
                         enclosed = !enclosed;
@@ -174,16 +348,35 @@ fn enclosed_tiles(map: &Vec<Vec<char>>, walls: Vec<(usize, usize)>) -> usize {
                     },
                     _ => {}
                 }
-            } else {
-                if enclosed { count += 1 }
+            } else if enclosed {
+                tiles[y][x] = Tile::Inside;
             }
         }
     }
 
-    return count;
+    return tiles;
 }
 
-fn nest_zone() -> usize {
+fn enclosed_tiles(map: &[Vec<char>], pipe_loop: &PipeLoop) -> usize {
+    // Count enclosed tiles by scanning across each line of the map.
+
+    return classify_tiles(map, pipe_loop)
+        .iter()
+        .map(|line| line.iter().filter(|tile| **tile == Tile::Inside).count())
+        .sum();
+}
+
+fn interior_tiles(pipe_loop: &PipeLoop) -> usize {
+    // Count enclosed tiles in linear time, using the loop's tiles as polygon vertices.
+    // The shoelace formula gives the loop's area A, then Pick's theorem, A = i + b/2 - 1, gives
+    // the interior points i, with each of the b loop tiles as a boundary point.
+
+    let doubled_area = signed_area(&pipe_loop.tiles).unsigned_abs();
+
+    return (doubled_area + 2 - pipe_loop.tiles.len()) / 2;
+}
+
+fn nest_zone(path: &str, method: Method, start: Option<(usize, usize)>) -> usize {
     // Find how much empty space is in the loop.
     //
     // The hard part of this is not counting elements which aren't actually inside the loop.
@@ -195,26 +388,163 @@ fn nest_zone() -> usize {
     //   L-JL-J
     // Even though .. looks "inside" the loop, it's not "inside" because of the dual walls.
 
-    if let Some(pipes) = fs::read_to_string("data/10.input").ok() {
-        let map: Vec<Vec<char>> = pipes
-            .lines()
-            .map(|line| line.chars().collect())
-            .collect();
+    let map = get_map(path);
+    let enclosing_loop = main_loop(&map, start);
 
-        // Get starting point.
-        if let Some(start) = get_start(&map) {
-            let enclosing_loop = get_loop(&map, start);
+    return match method {
+        Method::Shoelace => interior_tiles(&enclosing_loop),
+        Method::Scanline => enclosed_tiles(&map, &enclosing_loop)
+    };
+}
 
-            return enclosed_tiles(&map, enclosing_loop);
-        } else {
-            panic!("No 'S' starting point found")
-        }
-    }
+fn describe_loop(start: Option<(usize, usize)>) {
+    // Print the main loop, in order.
+
+    let map = get_map("data/10.input");
+    let pipe_loop = main_loop(&map, start);
+
+    println!(
+        "{} tiles, running {}, with the start acting as {}",
+        pipe_loop.tiles.len(),
+        if pipe_loop.clockwise { "clockwise" } else { "anticlockwise" },
+        pipe_loop.start_shape
+    );
 
-    panic!("file not found")
+    pipe_loop.tiles.iter().for_each(|(x, y)| println!("{x},{y} {}", map[*y][*x]));
+}
+
+fn describe_loops() {
+    // Print a summary of every loop in the map.
+
+    let map = get_map("data/10.input");
+
+    find_loops(&map).iter().for_each(|pipe_loop| {
+        let (x, y) = pipe_loop.tiles[0];
+
+        println!(
+            "loop from {x},{y}: length {}, area {}, {} enclosed tiles",
+            pipe_loop.tiles.len(),
+            signed_area(&pipe_loop.tiles).unsigned_abs() / 2,
+            interior_tiles(pipe_loop)
+        );
+    });
+}
+
+fn box_drawing(pipe: char) -> char {
+    return match pipe {
+        '|' => '│',
+        '-' => '─',
+        'F' => '┌',
+        '7' => '┐',
+        'L' => '└',
+        'J' => '┘',
+        other => other
+    };
+}
+
+fn render_map(colour: bool) -> String {
+    // Draw the map with box-drawing pipes, marking each other tile I or O depending on whether
+    // it's inside the main loop.
+    //
+    // With colour, the loop is highlighted and pipes which aren't part of it are dimmed, tinted
+    // by which side of the loop they're on. Without, they're simply marked I or O.
+
+    let map = get_map("data/10.input");
+    let pipe_loop = main_loop(&map, None);
+    let tiles = classify_tiles(&map, &pipe_loop);
+    let shapes = loop_shapes(&map, &pipe_loop);
+
+    return map.iter().zip(tiles.iter()).enumerate().map(|(y, (line, states))| {
+        line.iter().zip(states.iter()).enumerate().map(|(x, (c, state))| {
+            let pipe = shapes[y][x].unwrap_or(*c);
+            let junk = !openings(pipe).is_empty();
+            let mark = if *state == Tile::Inside { 'I' } else { 'O' };
+
+            return match state {
+                Tile::Wall if colour => format!("\x1b[1;33m{}\x1b[0m", box_drawing(pipe)),
+                Tile::Wall => box_drawing(pipe).to_string(),
+                _ if !colour => mark.to_string(),
+                Tile::Inside if junk => format!("\x1b[2;32m{}\x1b[0m", box_drawing(pipe)),
+                Tile::Outside if junk => format!("\x1b[2;34m{}\x1b[0m", box_drawing(pipe)),
+                Tile::Inside => format!("\x1b[1;32m{mark}\x1b[0m"),
+                Tile::Outside => format!("\x1b[2;34m{mark}\x1b[0m")
+            };
+        }).collect::<String>() + "\n"
+    }).collect();
 }
 
 fn main() {
-    println!("part one: {}", loop_size());
-    println!("part two: {}", nest_zone());
+    // `cargo run --bin day10 -- loop [<x> <y>]` lists a loop's tiles in order,
+    // `cargo run --bin day10 -- loops` summarises every loop in the map,
+    // `cargo run --bin day10 -- from <x> <y>` solves for the loop through any tile instead of 'S',
+    // `cargo run --bin day10 -- scanline` counts enclosed tiles by scanning the map instead,
+    // `cargo run --bin day10 -- render [plain]` draws the map showing what's inside the loop.
+
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+    let coordinate = |x: &str, y: &str| match (x.parse::<usize>(), y.parse::<usize>()) {
+        (Ok(x), Ok(y)) => Some((x, y)),
+        _ => panic!("improper coordinate: {x},{y}")
+    };
+
+    match args.iter().map(|arg| arg.as_str()).collect::<Vec<&str>>()[..] {
+        ["loop"] => describe_loop(None),
+        ["loop", x, y] => describe_loop(coordinate(x, y)),
+        ["loops"] => describe_loops(),
+        ["render"] => print!("{}", render_map(true)),
+        ["render", "plain"] => print!("{}", render_map(false)),
+        ["scanline"] => {
+            println!("part one: {}", loop_size(None));
+            println!("part two: {}", nest_zone("data/10.input", Method::Scanline, None));
+        },
+        ["from", x, y] => {
+            println!("part one: {}", loop_size(coordinate(x, y)));
+            println!("part two: {}", nest_zone("data/10.input", Method::Shoelace, coordinate(x, y)));
+        },
+        _ => {
+            println!("part one: {}", loop_size(None));
+            println!("part two: {}", nest_zone("data/10.input", Method::Shoelace, None));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_enclosed(path: &str, expected: usize) {
+        // Both counting methods must find the known number of enclosed tiles.
+
+        assert_eq!(nest_zone(path, Method::Shoelace, None), expected, "{path}: shoelace");
+        assert_eq!(nest_zone(path, Method::Scanline, None), expected, "{path}: scanline");
+    }
+
+    #[test]
+    fn simple_loop() {
+        assert_enclosed("data/10.test", 1);
+    }
+
+    #[test]
+    fn squeezed_loop() {
+        assert_enclosed("data/10.test2", 4);
+    }
+
+    #[test]
+    fn junk_pipes() {
+        assert_enclosed("data/10.test3", 10);
+    }
+
+    #[test]
+    fn walk_through_branching_s() {
+        // 'S' has three connecting neighbours, but only acts as an 'L' in the loop, so walks
+        // from elsewhere must pass through it as one.
+
+        let map = "7F-7\n||.|\nLS-J\n....".lines().map(|line| line.chars().collect()).collect::<Vec<Vec<char>>>();
+
+        let loops = find_loops(&map);
+        assert_eq!(loops.len(), 1);
+        assert_eq!(loops[0].tiles.len(), 8);
+
+        assert!(get_loop(&map, (0, 0)).is_none());
+        assert_eq!(get_loop(&map, (1, 0)).map(|pipe_loop| pipe_loop.tiles.len()), Some(8));
+    }
 }
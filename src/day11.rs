@@ -1,68 +1,300 @@
 use std::fs;
+use std::fmt;
+use std::cmp::Ordering;
 
-fn manhattan(i: &(usize, usize), j: &(usize, usize)) -> usize {
-    return i.0.abs_diff(j.0) + i.1.abs_diff(j.1);
+#[derive(Clone, Copy)]
+enum Metric {
+    Manhattan,
+    Chebyshev,
+    Euclidean
 }
 
-fn galaxy_distances(expansion: usize) -> usize {
-    // Increase the size of any empty rows/columns by `expansion`,
-    // then get the sum of all distances between galaxys.
+impl Metric {
+    fn parse(name: &str) -> Metric {
+        return match name {
+            "manhattan" => Metric::Manhattan,
+            "chebyshev" => Metric::Chebyshev,
+            "euclidean" => Metric::Euclidean,
+            _ => panic!("improper metric: {name}")
+        };
+    }
 
-    if let Some(map) = fs::read_to_string("data/11.input").ok() {
-        let mut galaxies = vec![];
+    fn distance(&self, a: &(u128, u128), b: &(u128, u128)) -> Distance {
+        // Manhattan and Chebyshev stay exact, only Euclidean needs a float.
 
-        let mut empty_rows = vec![];
-        let mut empty_cols = vec![];
+        let (dx, dy) = (a.0.abs_diff(b.0), a.1.abs_diff(b.1));
 
-        // Get galaxies an empty zones.
-        for (y, line) in map.lines().enumerate() {
-            if line.chars().filter(|c| *c == '#').count() == 0 {
-                empty_rows.push(y);
-            }
+        return match self {
+            Metric::Manhattan => match dx.checked_add(dy) {
+                Some(distance) => Distance::Exact(distance),
+                None => panic!("distance overflows u128")
+            },
+            Metric::Chebyshev => Distance::Exact(dx.max(dy)),
+            Metric::Euclidean => Distance::Real((dx as f64).hypot(dy as f64))
+        };
+    }
+}
 
-            for (x, c) in line.chars().enumerate() {
-                if y == 0 && map.lines().filter(|l| l.chars().nth(x) == Some('#')).count() == 0 {
-                    empty_cols.push(x)
-                }
+#[derive(Clone, Copy, PartialEq)]
+enum Distance {
+    Exact(u128),
+    Real(f64)
+}
 
-                if c == '#' {
-                    galaxies.push((x, y));
-                }
-            }
-        }
+impl Distance {
+    fn as_f64(&self) -> f64 {
+        return match self {
+            Distance::Exact(distance) => *distance as f64,
+            Distance::Real(distance) => *distance
+        };
+    }
 
-        // Expand rows.
-        for row in empty_rows.iter().rev() {
-            for mut galaxy in galaxies.iter_mut() {
-                if galaxy.1 > *row { galaxy.1 += expansion }
-            }
-        }
+    fn compare(&self, other: &Distance) -> Ordering {
+        // Exact distances compare exactly, anything else as floats.
 
-        // Expand columns.
-        for col in empty_cols.iter().rev() {
-            for mut galaxy in galaxies.iter_mut() {
-                if galaxy.0 > *col { galaxy.0 += expansion }
-            }
-        }
+        return match (self, other) {
+            (Distance::Exact(a), Distance::Exact(b)) => a.cmp(b),
+            _ => self.as_f64().total_cmp(&other.as_f64())
+        };
+    }
+}
 
-        let mut distance_between_galaxies = 0;
+impl fmt::Display for Distance {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            Distance::Exact(distance) => write!(f, "{distance}"),
+            Distance::Real(distance) => write!(f, "{distance}")
+        };
+    }
+}
 
-        for i in galaxies.iter() {
-            for j in galaxies.iter() {
-                // Don't check combinations twice.
-                if i.0 < j.0 || (i.0 == j.0 && i.1 < j.1) {
-                    distance_between_galaxies += manhattan(i, j);
-                }
-            }
-        }
+fn expand(coordinates: &[usize], size: usize, factor: u128) -> Result<Vec<u128>, String> {
+    // Stretch one axis, so each empty line along it becomes `factor` lines.
+    // A prefix count of the empty lines gives each coordinate's new position in one pass.
+    // Positions past u128 are only an error if a galaxy lands on one.
+
+    let mut occupied = vec![false; size];
+    coordinates.iter().for_each(|c| occupied[*c] = true);
 
-        return distance_between_galaxies;
+    let mut positions = Vec::with_capacity(size);
+    let mut position: Option<u128> = Some(0);
+
+    for filled in occupied {
+        positions.push(position);
+        position = position.and_then(|p| p.checked_add(if filled { 1 } else { factor }));
+    }
+
+    return coordinates
+        .iter()
+        .map(|c| positions[*c].ok_or_else(|| "expanded position overflows u128".to_string()))
+        .collect();
+}
+
+fn axis_distances(mut coordinates: Vec<u128>) -> Result<u128, String> {
+    // Sum the distances between every pair of points along one axis.
+    // Once sorted, the i-th point is `x_i` past each of the i points before it, less their sum,
+    // so a running total does all pairs in O(n log n).
+
+    coordinates.sort();
+
+    let overflow = || "sum of distances overflows u128".to_string();
+    let mut before: u128 = 0;
+    let mut total: u128 = 0;
+
+    for (idx, c) in coordinates.iter().enumerate() {
+        // Each point is at least as far along as the ones before it, so this can't go negative.
+        let distance = c.checked_mul(idx as u128).ok_or_else(overflow)? - before;
+
+        total = total.checked_add(distance).ok_or_else(overflow)?;
+        before = before.checked_add(*c).ok_or_else(overflow)?;
+    }
+
+    return Ok(total);
+}
+
+fn get_galaxies(row_factor: u128, col_factor: u128) -> Result<Vec<(u128, u128)>, String> {
+    // Find each galaxy, then stretch the empty rows and columns by their factors.
+
+    if let Some(map) = fs::read_to_string("data/11.input").ok() {
+        let galaxies = map
+            .lines()
+            .enumerate()
+            .flat_map(|(y, line)| {
+                line.chars()
+                    .enumerate()
+                    .filter(|(_, c)| *c == '#')
+                    .map(move |(x, _)| (x, y))
+            }).collect::<Vec<(usize, usize)>>();
+
+        let width = map.lines().map(|line| line.len()).max().unwrap_or(0);
+        let height = map.lines().count();
+
+        let xs = expand(&galaxies.iter().map(|g| g.0).collect::<Vec<usize>>(), width, col_factor)?;
+        let ys = expand(&galaxies.iter().map(|g| g.1).collect::<Vec<usize>>(), height, row_factor)?;
+
+        return Ok(xs.into_iter().zip(ys).collect());
     }
 
     panic!("file not found")
 }
 
+fn galaxy_distances(row_factor: u128, col_factor: u128) -> Result<u128, String> {
+    // Each empty row becomes `row_factor` rows, and each empty column `col_factor` columns,
+    // then get the sum of all distances between galaxys.
+    // Manhattan distance splits by axis, so each axis is summed on its own.
+
+    let galaxies = get_galaxies(row_factor, col_factor)?;
+
+    let columns = axis_distances(galaxies.iter().map(|g| g.0).collect())?;
+    let rows = axis_distances(galaxies.iter().map(|g| g.1).collect())?;
+
+    return columns.checked_add(rows).ok_or_else(|| "sum of distances overflows u128".to_string());
+}
+
+fn ranked(galaxies: &[(u128, u128)], galaxy: usize, metric: Metric) -> Vec<(usize, Distance)> {
+    // Every other galaxy with its distance from `galaxy`, closest first.
+    // Ties keep the lower numbered galaxy first.
+
+    let mut others = galaxies
+        .iter()
+        .enumerate()
+        .filter(|(idx, _)| *idx != galaxy)
+        .map(|(idx, other)| (idx, metric.distance(&galaxies[galaxy], other)))
+        .collect::<Vec<(usize, Distance)>>();
+
+    others.sort_by(|a, b| a.1.compare(&b.1));
+
+    return others;
+}
+
+fn distance_matrix(galaxies: &[(u128, u128)], metric: Metric) -> String {
+    // Every pairwise distance as CSV, with galaxies numbered from 1 along both headers.
+
+    let header = (1..=galaxies.len()).map(|idx| idx.to_string()).collect::<Vec<String>>().join(",");
+    let mut csv = format!("galaxy,{header}\n");
+
+    galaxies.iter().enumerate().for_each(|(idx, galaxy)| {
+        let row = galaxies
+            .iter()
+            .map(|other| metric.distance(galaxy, other).to_string())
+            .collect::<Vec<String>>()
+            .join(",");
+
+        csv.push_str(&format!("{},{row}\n", idx + 1));
+    });
+
+    return csv;
+}
+
+fn distance_histogram(galaxies: &[(u128, u128)], metric: Metric, buckets: usize) -> String {
+    // Split the range of pairwise distances into equal buckets, and draw a bar for each.
+
+    let distances = galaxies
+        .iter()
+        .enumerate()
+        .flat_map(|(idx, galaxy)| galaxies[idx + 1..].iter().map(|other| metric.distance(galaxy, other).as_f64()))
+        .collect::<Vec<f64>>();
+
+    if distances.is_empty() || buckets == 0 {
+        return String::new();
+    }
+
+    let low = distances.iter().copied().fold(f64::INFINITY, f64::min);
+    let high = distances.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let width = (high - low) / buckets as f64;
+
+    let mut counts = vec![0; buckets];
+
+    distances.iter().for_each(|distance| {
+        let bucket = if width > 0.0 { ((distance - low) / width) as usize } else { 0 };
+        counts[bucket.min(buckets - 1)] += 1;
+    });
+
+    let most = counts.iter().copied().max().unwrap_or(1);
+
+    return counts
+        .iter()
+        .enumerate()
+        .map(|(idx, count)| {
+            let from = low + width * idx as f64;
+            let bar = "#".repeat(count * 50 / most);
+
+            format!("{:>12.1} - {:<12.1} {count:>8} {bar}\n", from, from + width)
+        }).collect();
+}
+
 fn main() {
-    println!("part one: {}", galaxy_distances(1));
-    println!("part two: {}", galaxy_distances(999_999));
+    // `cargo run --bin day11 -- <row factor> <col factor>` expands each axis separately.
+    // Queries may follow the factors, which default to 2:
+    // `nearest <galaxy> [metric]`, `farthest <galaxy> [metric]`, `matrix [metric]`,
+    // `histogram [metric] [buckets]`. Galaxies are numbered from 1, and the metric is one of
+    // manhattan (default), chebyshev or euclidean.
+
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+    let factor = |arg: &str| match arg.parse::<u128>() {
+        Ok(factor) => factor,
+        Err(_) => panic!("improper factor: {arg}")
+    };
+
+    let (factors, query) = match args.iter().map(|arg| arg.as_str()).collect::<Vec<&str>>()[..] {
+        [rows, cols, ..] if rows.parse::<u128>().is_ok() => ((factor(rows), factor(cols)), &args[2..]),
+        _ => ((2, 2), &args[..])
+    };
+
+    let galaxy = |arg: &str| match arg.parse::<usize>() {
+        Ok(galaxy) if galaxy >= 1 => galaxy - 1,
+        _ => panic!("improper galaxy: {arg}")
+    };
+
+    let query = query.iter().map(|arg| arg.as_str()).collect::<Vec<&str>>();
+
+    let fail = |err: String| -> ! {
+        eprintln!("error: {err}");
+        std::process::exit(1);
+    };
+    let galaxies = || get_galaxies(factors.0, factors.1).unwrap_or_else(|err| fail(err));
+    let report = |label: &str, row_factor: u128, col_factor: u128| match galaxy_distances(row_factor, col_factor) {
+        Ok(distances) => println!("{label}: {distances}"),
+        Err(err) => fail(err)
+    };
+
+    let metric = match query[..] {
+        ["nearest" | "farthest", _, name] | ["matrix" | "histogram", name, ..]
+            if name.parse::<usize>().is_err() => Metric::parse(name),
+        _ => Metric::Manhattan
+    };
+
+    match query[..] {
+        [order @ ("nearest" | "farthest"), from, ..] => {
+            let galaxies = galaxies();
+            let from = galaxy(from);
+
+            if from >= galaxies.len() {
+                panic!("there are only {} galaxies", galaxies.len());
+            }
+
+            let others = ranked(&galaxies, from, metric);
+            // The farthest distance can be shared, so take the first galaxy at it, as for nearest.
+            let found = match (order, others.last()) {
+                ("farthest", Some((_, farthest))) => others.iter().find(|(_, distance)| distance == farthest),
+                _ => others.first()
+            };
+
+            match found {
+                Some((other, distance)) => println!("{order}: galaxy {} at {distance}", other + 1),
+                None => println!("{order}: no other galaxies")
+            }
+        },
+        ["matrix", ..] => print!("{}", distance_matrix(&galaxies(), metric)),
+        ["histogram", ..] => {
+            let buckets = query[1..].iter().find_map(|arg| arg.parse::<usize>().ok()).unwrap_or(10);
+
+            print!("{}", distance_histogram(&galaxies(), metric, buckets));
+        },
+        [] if !args.is_empty() => report("distances", factors.0, factors.1),
+        _ => {
+            report("part one", 2, 2);
+            report("part two", 1_000_000, 1_000_000);
+        }
+    }
 }
@@ -1,129 +1,548 @@
 use std::fs;
-use std::collections::HashMap;
 use std::iter::repeat;
 
-fn walk_record(
-    diagram: &Vec<char>,
-    goal: &Vec<usize>,
-    accrual: usize,
-    cursor: usize,
-    goal_cur: usize,
-    cache: &mut HashMap<(usize, usize, usize), usize>
-) -> usize {
-    // Walk the diagram with an accrual system.
-
-    if let Some(cache_entry) = cache.get(&(accrual, cursor, goal_cur)) {
-        return *cache_entry;
-    }
-
-    if cursor == diagram.len() {
-        if goal_cur == goal.len() {
-            return 1;
-        } else {
-            if goal_cur == goal.len() - 1 && accrual == goal[goal_cur] {
-                return 1;
+struct Record {
+    diagram: Vec<char>,
+    goal: Vec<usize>,
+    // `ways[cursor * (goal.len() + 1) + goal_cur]` counts the arrangements of `diagram[cursor..]`
+    // using the runs `goal[goal_cur..]`, starting between two runs. `None` if that overflows.
+    ways: Vec<Option<u128>>
+}
+
+impl Record {
+    fn new(diagram: Vec<char>, goal: Vec<usize>) -> Result<Record, String> {
+        // Fill the table from the end of the diagram backwards. At each cursor either the
+        // spring is a ., or the next run starts here and is followed by a . or the end.
+        //
+        // Counts are checked, and an overflow spreads as `None` through every count built on it.
+        // Cells the walk from the start never reaches can overflow harmlessly, so it's only an
+        // error when the overflow reaches the total.
+
+        let width = goal.len() + 1;
+        let mut ways = vec![Some(0); (diagram.len() + 1) * width];
+        ways[diagram.len() * width + goal.len()] = Some(1);
+
+        // How many . springs come before each cursor, to check a run fits in one step.
+        let mut dots = vec![0; diagram.len() + 1];
+        diagram.iter().enumerate().for_each(|(idx, c)| dots[idx + 1] = dots[idx] + (*c == '.') as usize);
+
+        for cursor in (0..diagram.len()).rev() {
+            for goal_cur in 0..width {
+                let mut count: Option<u128> = Some(0);
+
+                if diagram[cursor] != '#' {
+                    count = ways[(cursor + 1) * width + goal_cur];
+                }
+
+                if goal_cur < goal.len() {
+                    let end = cursor + goal[goal_cur];
+
+                    if end <= diagram.len()
+                        && dots[end] == dots[cursor]
+                        && diagram.get(end) != Some(&'#')
+                    {
+                        let next = (end + 1).min(diagram.len());
+
+                        count = count
+                            .zip(ways[next * width + goal_cur + 1])
+                            .and_then(|(count, run)| count.checked_add(run));
+                    }
+                }
+
+                ways[cursor * width + goal_cur] = count;
             }
+        }
 
-            return 0;
+        if ways[0].is_none() {
+            return Err("arrangements overflow u128".to_string());
         }
+
+        return Ok(Record { diagram, goal, ways });
     }
 
-    if goal_cur == goal.len() {
-        if diagram
-            .iter()
-            .enumerate()
-            .filter(|(idx, v)| idx >= &cursor && **v == '#')
-            .count() == 0
-        {
-            return 1;
-        } else {
-            return 0;
-        }
+    fn ways(&self, cursor: usize, goal_cur: usize) -> u128 {
+        // Anything the walk from the start reaches is no bigger than the total, which fit.
+
+        return match self.ways[cursor * (self.goal.len() + 1) + goal_cur] {
+            Some(ways) => ways,
+            None => panic!("arrangements from spring {cursor} overflow u128")
+        };
     }
 
-    return match diagram[cursor..] {
-        ['#', ..] => {
-            if accrual == goal[goal_cur] {
-                return 0;
-            } else {
-                let result = walk_record(diagram, goal, accrual + 1, cursor + 1, goal_cur, cache);
+    fn count(&self) -> u128 {
+        return self.ways(0, 0);
+    }
 
-                return result;
+    fn unrank(&self, mut rank: u128) -> String {
+        // Build the `rank`-th valid arrangement. At each free choice every arrangement with a .
+        // comes before every one starting a run, so the table says which way the rank lies.
+
+        let mut arrangement = String::with_capacity(self.diagram.len());
+        let mut cursor = 0;
+        let mut goal_cur = 0;
+
+        while cursor < self.diagram.len() {
+            let dots = if self.diagram[cursor] != '#' { self.ways(cursor + 1, goal_cur) } else { 0 };
+
+            if rank < dots {
+                arrangement.push('.');
+                cursor += 1;
+                continue;
             }
-        },
-        ['.', ..] => {
-            if accrual == goal[goal_cur] {
-                let result = walk_record(diagram, goal, 0, cursor + 1, goal_cur + 1, cache);
 
-                return result;
-            } else if accrual == 0 {
-                let result = walk_record(diagram, goal, accrual, cursor + 1, goal_cur, cache);
+            rank -= dots;
+
+            let end = cursor + self.goal[goal_cur];
+            arrangement.push_str(&"#".repeat(self.goal[goal_cur]));
 
-                return result;
-            } else {
-                return 0;
+            if end < self.diagram.len() {
+                arrangement.push('.');
             }
-        },
-        ['?', ..] => {
-            if accrual == goal[goal_cur] {
-                // Found our goal, treat ? as .
-                // drop(goal.pop_front());
-                let result = walk_record(diagram, goal, 0, cursor + 1, goal_cur + 1, cache);
-
-                return result;
-            } else if accrual > 0 {
-                // We need to keep walking.
-                let result = walk_record(diagram, goal, accrual + 1, cursor + 1, goal_cur, cache);
-
-                return result;
-            } else {
-                // Test each option.
-                let result =
-                    walk_record(diagram, goal, 0, cursor + 1, goal_cur, cache) +           // .
-                    walk_record(diagram, goal, accrual + 1, cursor + 1, goal_cur, cache);  // #
-
-                cache.insert((accrual, cursor, goal_cur), result);
-
-                return result;
+
+            cursor = end + 1;
+            goal_cur += 1;
+        }
+
+        return arrangement;
+    }
+}
+
+struct Arrangements {
+    record: Record,
+    rank: u128,
+    end: u128
+}
+
+impl Iterator for Arrangements {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        // Each arrangement is only built when asked for.
+
+        if self.rank == self.end {
+            return None;
+        }
+
+        let arrangement = self.record.unrank(self.rank);
+        self.rank += 1;
+
+        return Some(arrangement);
+    }
+}
+
+fn arrangements(record: Record, limit: u128) -> Arrangements {
+    // Lazily list up to `limit` of the valid arrangements of a record, in order.
+
+    let end = record.count().min(limit);
+
+    return Arrangements { record, rank: 0, end };
+}
+
+struct Random {
+    state: u64
+}
+
+impl Random {
+    fn new(seed: u64) -> Random {
+        // xorshift can't leave a zero state, so nudge it.
+
+        return Random { state: seed.max(1) };
+    }
+
+    fn next(&mut self) -> u64 {
+        // xorshift64*
+
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+
+        return self.state.wrapping_mul(0x2545_f491_4f6c_dd1d);
+    }
+
+    fn below(&mut self, bound: u128) -> u128 {
+        // Reject draws from the incomplete last stretch of the range, so no value is favoured.
+
+        let zone = u128::MAX - u128::MAX % bound;
+
+        loop {
+            let draw = (self.next() as u128) << 64 | self.next() as u128;
+
+            if draw < zone {
+                return draw % bound;
             }
-        },
-        _ => 0
+        }
+    }
+}
+
+fn sample_record(record: &Record, random: &mut Random) -> Option<String> {
+    // Pick a uniformly random valid arrangement, by picking a random rank.
+    // Only the counts are needed, so this works on records far too big to list.
+
+    if record.count() == 0 {
+        return None;
+    }
+
+    return Some(record.unrank(random.below(record.count())));
+}
+
+fn parse_record(line: &str, n: usize) -> Option<(Vec<char>, Vec<usize>)> {
+    // Read a spring record, repeated N times.
+
+    if let Some((diagram, goal)) = line.split_once(" ") {
+        let mut diagram = repeat(
+                diagram.chars().chain("?".chars())
+            ).take(n).flatten().collect::<Vec<char>>();
+        drop(diagram.pop());
+
+        let goal = repeat(
+                goal.split(",").filter_map(|c| c.parse::<usize>().ok())
+            ).take(n).flatten().collect();
+
+        return Some((diagram, goal));
     }
+
+    None
+}
+
+fn get_record(number: usize, n: usize) -> Result<Record, String> {
+    // Pick out a single record, numbered from 1.
+
+    if n == 0 {
+        return Err("repeats must be at least 1".to_string());
+    }
+
+    if let Some(input) = fs::read_to_string("data/12.input").ok() {
+        if let Some((diagram, goal)) = input.lines().nth(number.wrapping_sub(1)).and_then(|line| parse_record(line, n)) {
+            return Record::new(diagram, goal);
+        }
+
+        panic!("no record {number}");
+    }
+
+    panic!("file not found")
 }
 
-fn n_nonogram_combinations(n: usize) -> usize {
+fn n_nonogram_combinations(n: usize) -> Result<u128, String> {
     // How many different ways could a spring record be put together, when repeated N times?
 
+    if n == 0 {
+        return Err("repeats must be at least 1".to_string());
+    }
+
     if let Some(input) = fs::read_to_string("data/12.input").ok() {
-        return input
-            .lines()
-            .filter_map(|line| {
-                if let Some((diagram, goal)) = line.split_once(" ") {
-                    let mut diagram = repeat(
-                            diagram.chars().chain("?".chars())
-                        ).take(n).flatten().collect::<Vec<char>>();
-                    drop(diagram.pop());
-
-                    let goal = repeat(
-                            goal.split(",").filter_map(|c| c.parse::<usize>().ok())
-                        ).take(n).flatten().collect();
-
-
-                    // Using the cache speeds up the splitting operation by an unbelievable margin.
-                    let mut cache = HashMap::new();
-
-                    return Some(walk_record(&diagram, &goal, 0, 0, 0, &mut cache));
-                } else {
-                    return None;
+        let mut total: u128 = 0;
+
+        for (idx, line) in input.lines().enumerate() {
+            if let Some((diagram, goal)) = parse_record(line, n) {
+                let count = Record::new(diagram, goal).map_err(|err| format!("line {}: {err}", idx + 1))?.count();
+
+                total = match total.checked_add(count) {
+                    Some(total) => total,
+                    None => return Err(format!("line {}: total arrangements overflow u128", idx + 1))
+                };
+            }
+        }
+
+        return Ok(total);
+    }
+
+    panic!("file not found")
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Cell {
+    Unknown,
+    Filled,
+    Empty
+}
+
+struct Nonogram {
+    rows: Vec<Vec<usize>>,
+    cols: Vec<Vec<usize>>
+}
+
+fn load_nonogram(path: &str) -> Nonogram {
+    // Row clues, a blank line, then column clues. Each clue is a comma separated list of run
+    // lengths, and a line with no runs is written as 0.
+
+    if let Some(input) = fs::read_to_string(path).ok() {
+        if let Some((rows, cols)) = input.split_once("\n\n") {
+            let clues = |section: &str| {
+                section
+                    .lines()
+                    .map(|line| {
+                        line.split(",")
+                            .map(|run| match run.trim().parse::<usize>() {
+                                Ok(run) => run,
+                                Err(_) => panic!("improper clue: {line}")
+                            })
+                            .filter(|run| *run > 0)
+                            .collect::<Vec<usize>>()
+                    }).collect::<Vec<Vec<usize>>>()
+            };
+
+            return Nonogram { rows: clues(rows), cols: clues(cols) };
+        }
+
+        panic!("improper file format");
+    }
+
+    panic!("file not found")
+}
+
+fn solve_line(line: &[Cell], clue: &[usize]) -> Option<Vec<Cell>> {
+    // Treat the line as a spring record, with unknown cells as ?, and count its arrangements.
+    // An unknown cell is settled when filling it leaves none of them, or all of them.
+    // Returns None if the line can't be completed at all.
+
+    let mut diagram = line
+        .iter()
+        .map(|cell| match cell {
+            Cell::Unknown => '?',
+            Cell::Filled => '#',
+            Cell::Empty => '.'
+        }).collect::<Vec<char>>();
+
+    let count = |diagram: &[char]| match Record::new(diagram.to_vec(), clue.to_vec()) {
+        Ok(record) => record.count(),
+        Err(err) => panic!("{err}")
+    };
+
+    let total = count(&diagram);
+
+    if total == 0 {
+        return None;
+    }
+
+    let mut solved = line.to_vec();
+
+    for idx in 0..line.len() {
+        if line[idx] != Cell::Unknown {
+            continue;
+        }
+
+        diagram[idx] = '#';
+        let filled = count(&diagram);
+        diagram[idx] = '?';
+
+        if filled == 0 {
+            solved[idx] = Cell::Empty;
+        } else if filled == total {
+            solved[idx] = Cell::Filled;
+        }
+    }
+
+    return Some(solved);
+}
+
+fn propagate(puzzle: &Nonogram, grid: &mut [Vec<Cell>]) -> bool {
+    // Solve every row and column in turn until nothing more can be settled.
+    // Returns false if some line turns out to be impossible.
+
+    let mut changed = true;
+
+    while changed {
+        changed = false;
+
+        for (y, clue) in puzzle.rows.iter().enumerate() {
+            match solve_line(&grid[y], clue) {
+                Some(row) => if row != grid[y] {
+                    grid[y] = row;
+                    changed = true;
+                },
+                None => return false
+            }
+        }
+
+        for (x, clue) in puzzle.cols.iter().enumerate() {
+            let col = grid.iter().map(|row| row[x]).collect::<Vec<Cell>>();
+
+            match solve_line(&col, clue) {
+                Some(solved) => if solved != col {
+                    grid.iter_mut().zip(solved).for_each(|(row, cell)| row[x] = cell);
+                    changed = true;
+                },
+                None => return false
+            }
+        }
+    }
+
+    return true;
+}
+
+fn solve_nonogram(puzzle: &Nonogram, mut grid: Vec<Vec<Cell>>) -> Option<Vec<Vec<Cell>>> {
+    // Propagate as far as line solving allows, then guess the first unknown cell and backtrack
+    // if the guess leads to a contradiction.
+
+    if !propagate(puzzle, &mut grid) {
+        return None;
+    }
+
+    let unknown = grid.iter().enumerate().find_map(|(y, row)| {
+        row.iter().position(|cell| *cell == Cell::Unknown).map(|x| (x, y))
+    });
+
+    if let Some((x, y)) = unknown {
+        return [Cell::Filled, Cell::Empty].iter().find_map(|guess| {
+            let mut attempt = grid.clone();
+            attempt[y][x] = *guess;
+
+            solve_nonogram(puzzle, attempt)
+        });
+    }
+
+    return Some(grid);
+}
+
+fn render_nonogram(puzzle: &Nonogram, grid: &[Vec<Cell>]) -> String {
+    // Draw the grid with its row clues alongside, and the column clues stacked underneath.
+
+    let clue = |runs: &Vec<usize>| {
+        if runs.is_empty() {
+            return "0".to_string();
+        }
+
+        return runs.iter().map(|run| run.to_string()).collect::<Vec<String>>().join(",");
+    };
+
+    let mut render = grid
+        .iter()
+        .zip(puzzle.rows.iter())
+        .map(|(row, runs)| {
+            let cells = row
+                .iter()
+                .map(|cell| match cell {
+                    Cell::Unknown => '?',
+                    Cell::Filled => '#',
+                    Cell::Empty => '.'
+                }).collect::<String>();
+
+            format!("{cells}  {}\n", clue(runs))
+        }).collect::<String>();
+
+    let depth = puzzle.cols.iter().map(|runs| runs.len().max(1)).max().unwrap_or(0);
+
+    for level in 0..depth {
+        let line = puzzle.cols
+            .iter()
+            .map(|runs| {
+                let runs = if runs.is_empty() { vec![0] } else { runs.clone() };
+
+                match runs.get(level) {
+                    Some(run) if *run < 10 => char::from_digit(*run as u32, 10).unwrap_or('?'),
+                    Some(_) => '+',
+                    None => ' '
                 }
             })
-            .sum::<usize>();
+            .collect::<String>();
+
+        render.push_str(line.trim_end());
+        render.push('\n');
     }
 
-    panic!("file not found")
+    return render;
+}
+
+fn nonogram(path: &str) {
+    // Solve a full 2D nonogram, treating each row and column like a spring record.
+
+    let puzzle = load_nonogram(path);
+    let grid = vec![vec![Cell::Unknown; puzzle.cols.len()]; puzzle.rows.len()];
+
+    match solve_nonogram(&puzzle, grid) {
+        Some(solution) => print!("{}", render_nonogram(&puzzle, &solution)),
+        None => println!("{path} has no solution")
+    }
 }
 
 fn main() {
-    println!("part one: {}", n_nonogram_combinations(1));
-    println!("part two: {}", n_nonogram_combinations(5));
+    // `cargo run --bin day12 -- --unfold <repeats>` counts with any number of repeats.
+    // `list <record> [limit] [repeats]` lists a record's arrangements,
+    // `sample <record> [repeats] [seed]` picks one at random. Records are numbered from 1.
+    // `nonogram [clue file]` solves a 2D nonogram, by default `data/12.nonogram`.
+
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+    let number = |arg: &str| match arg.parse::<usize>() {
+        Ok(number) => number,
+        Err(_) => panic!("improper number: {arg}")
+    };
+    let record = |number: usize, repeats: usize| match get_record(number, repeats) {
+        Ok(record) => record,
+        Err(err) => {
+            eprintln!("record {number}: {err}");
+            std::process::exit(1);
+        }
+    };
+    let report = |label: &str, count: Result<u128, String>| match count {
+        Ok(count) => println!("{label}: {count}"),
+        Err(err) => {
+            eprintln!("{label}: {err}");
+            std::process::exit(1);
+        }
+    };
+
+    match args.iter().map(|arg| arg.as_str()).collect::<Vec<&str>>()[..] {
+        ["--unfold", repeats] => report(&format!("unfolded {repeats}"), n_nonogram_combinations(number(repeats))),
+        ["list", number_, ref rest @ ..] => {
+            let limit = rest.first().map_or(20, |limit| number(limit)) as u128;
+            let repeats = rest.get(1).map_or(1, |repeats| number(repeats));
+
+            arrangements(record(number(number_), repeats), limit).for_each(|arrangement| println!("{arrangement}"));
+        },
+        ["sample", number_, ref rest @ ..] => {
+            let repeats = rest.first().map_or(1, |repeats| number(repeats));
+            let seed = match rest.get(1) {
+                Some(seed) => number(seed) as u64,
+                None => std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map_or(1, |time| time.as_nanos() as u64)
+            };
+
+            match sample_record(&record(number(number_), repeats), &mut Random::new(seed)) {
+                Some(arrangement) => println!("{arrangement}"),
+                None => println!("record {number_} has no valid arrangements")
+            }
+        },
+        ["nonogram"] => nonogram("data/12.nonogram"),
+        ["nonogram", path] => nonogram(path),
+        _ => {
+            report("part one", n_nonogram_combinations(1));
+            report("part two", n_nonogram_combinations(5));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unreachable_overflow() {
+        // Placing 75 of the runs anywhere from the very start overflows, but only one
+        // arrangement actually fits all 150.
+
+        let record = Record::new(vec!['?'; 299], vec![1; 150]).expect("only one arrangement");
+
+        assert_eq!(record.count(), 1);
+        assert_eq!(arrangements(record, 2).collect::<Vec<String>>(), vec!["#.".repeat(149) + "#"]);
+    }
+
+    #[test]
+    fn unreachable_overflow_in_nonogram_line() {
+        let line = solve_line(&[Cell::Unknown; 299], &[1; 150]).expect("line is solvable");
+
+        assert!(line.iter().step_by(2).all(|cell| *cell == Cell::Filled));
+        assert!(line.iter().skip(1).step_by(2).all(|cell| *cell == Cell::Empty));
+    }
+
+    #[test]
+    fn real_overflow() {
+        assert!(Record::new(vec!['?'; 400], vec![1; 100]).is_err());
+    }
+
+    #[test]
+    fn zero_repeats() {
+        assert!(n_nonogram_combinations(0).is_err());
+        assert!(get_record(1, 0).is_err());
+    }
 }
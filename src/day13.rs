@@ -1,150 +1,391 @@
 use std::fs;
+use std::time::Instant;
+
+trait Line: PartialEq {
+    // How many cells differ from another line of the same length, and where.
+    fn differences(&self, other: &Self) -> usize;
+    fn differing_cells(&self, other: &Self) -> Vec<usize>;
+}
+
+impl Line for Vec<bool> {
+    fn differences(&self, other: &Self) -> usize {
+        return self.iter().zip(other).filter(|(l, r)| l != r).count();
+    }
+
+    fn differing_cells(&self, other: &Self) -> Vec<usize> {
+        return (0..self.len()).filter(|cell| self[*cell] != other[*cell]).collect();
+    }
+}
+
+fn set_bits(mut bits: u128, offset: usize, cells: &mut Vec<usize>) {
+    // Push the position of every set bit, lowest first.
+
+    while bits != 0 {
+        cells.push(offset + bits.trailing_zeros() as usize);
+        bits &= bits - 1;
+    }
+}
+
+impl Line for u64 {
+    fn differences(&self, other: &Self) -> usize {
+        return (self ^ other).count_ones() as usize;
+    }
+
+    fn differing_cells(&self, other: &Self) -> Vec<usize> {
+        let mut cells = vec![];
+        set_bits((self ^ other) as u128, 0, &mut cells);
+
+        return cells;
+    }
+}
+
+impl Line for u128 {
+    fn differences(&self, other: &Self) -> usize {
+        return (self ^ other).count_ones() as usize;
+    }
+
+    fn differing_cells(&self, other: &Self) -> Vec<usize> {
+        let mut cells = vec![];
+        set_bits(self ^ other, 0, &mut cells);
+
+        return cells;
+    }
+}
+
+impl Line for Vec<u128> {
+    // Lines too long for one mask are split into 128 cell chunks.
+
+    fn differences(&self, other: &Self) -> usize {
+        return self.iter().zip(other).map(|(l, r)| (l ^ r).count_ones() as usize).sum();
+    }
+
+    fn differing_cells(&self, other: &Self) -> Vec<usize> {
+        let mut cells = vec![];
+        self.iter().zip(other).enumerate().for_each(|(chunk, (l, r))| set_bits(l ^ r, chunk * 128, &mut cells));
+
+        return cells;
+    }
+}
+
+enum Plane {
+    // One cell per bool, as the patterns were first stored.
+    Cells(Vec<Vec<bool>>),
+    // Lines as bitmasks, with cell i in bit i, using the smallest mask that fits.
+    Narrow(Vec<u64>),
+    Wide(Vec<u128>),
+    Chunked(Vec<Vec<u128>>)
+}
+
+impl Plane {
+    fn encode(lines: Vec<Vec<bool>>) -> Plane {
+        let length = lines.first().map_or(0, |line| line.len());
+        let chunks = lines
+            .iter()
+            .map(|line| {
+                let mut chunks = vec![0u128; length.div_ceil(128)];
+                line.iter().enumerate().filter(|(_, cell)| **cell).for_each(|(idx, _)| chunks[idx / 128] |= 1 << (idx % 128));
+
+                chunks
+            }).collect::<Vec<Vec<u128>>>();
+
+        return match length {
+            0..=64 => Plane::Narrow(chunks.iter().map(|line| line.first().map_or(0, |mask| *mask as u64)).collect()),
+            65..=128 => Plane::Wide(chunks.iter().map(|line| line[0]).collect()),
+            _ => Plane::Chunked(chunks)
+        };
+    }
+
+    fn search(&self, k: usize) -> Option<(usize, Vec<(usize, usize)>)> {
+        return match self {
+            Plane::Cells(lines) => search_for_seam(lines, k),
+            Plane::Narrow(lines) => search_for_seam(lines, k),
+            Plane::Wide(lines) => search_for_seam(lines, k),
+            Plane::Chunked(lines) => search_for_seam(lines, k)
+        };
+    }
+}
 
 struct Mirror {
-    horizontal: Vec<Vec<bool>>,
-    vertical: Vec<Vec<bool>>
+    pattern: Vec<Vec<bool>>,
+    horizontal: Plane,
+    vertical: Plane
+}
+
+fn build_mirror(pattern: Vec<Vec<bool>>, encode: fn(Vec<Vec<bool>>) -> Plane) -> Mirror {
+    // Encode the rows of the pattern, alongside its transposition.
+
+    let mut vertical = vec![];
+    for x in 0..pattern[0].len() {
+        vertical.push(vec![]);
+        for row in pattern.iter() {
+            vertical[x].push(row[x]);
+        }
+    }
+
+    return Mirror { horizontal: encode(pattern.clone()), vertical: encode(vertical), pattern };
 }
 
 fn get_mirrors(mirrors: String) -> Vec<Mirror> {
     mirrors.split("\n\n").map(|mirror| {
-        // Generate the horizontal mirror, alongside its transposition.
-
-        let horizontal = mirror.lines().map(|line| {
+        let pattern = mirror.lines().map(|line| {
             return line.chars().map(|c| c == '#').collect();
         }).collect::<Vec<Vec<bool>>>();
 
-        let mut vertical = vec![];
-        for x in 0..horizontal[0].len() {
-            vertical.push(vec![]);
-            for y in 0..horizontal.len() {
-                vertical[x].push(horizontal[y][x]);
-            }
-        }
-
-        return Mirror { horizontal, vertical };
+        return build_mirror(pattern, Plane::encode);
     }).collect::<Vec<Mirror>>()
 }
 
-fn search_for_seam(plane: &Vec<Vec<bool>>) -> Option<usize> {
-    // Find the first idx which can reduce into a reflection.
+#[derive(Clone, Copy)]
+enum Orientation {
+    Horizontal,
+    Vertical
+}
+
+struct Reflection {
+    // The seam lies after line `seam`.
+    seam: usize,
+    orientation: Orientation,
+    // Every smudge tolerated to make the reflection, as (x, y) in the pattern.
+    smudges: Vec<(usize, usize)>
+}
+
+impl Reflection {
+    fn score(&self) -> usize {
+        // 100 * lines above for horizontal mirrors, 1 * lines left of it for vertical mirrors.
+
+        return match self.orientation {
+            Orientation::Horizontal => (self.seam + 1) * 100,
+            Orientation::Vertical => self.seam + 1
+        };
+    }
+}
+
+fn search_for_seam<L: Line>(plane: &[L], k: usize) -> Option<(usize, Vec<(usize, usize)>)> {
+    // Find the first idx which reduces into a reflection with exactly k differences.
+    // Each difference is a smudge, returned as (line, cell) on the side nearer the start.
+    // Equal lines are a single compare, so cells are only pinpointed once a seam is found.
 
     for idx in 0..(plane.len() - 1) {
-        let mut left = idx;
-        let mut right = left + 1;
-        let mut found = false;
+        let mut differences = 0;
+        let mut smudged = vec![];
+        let reach = (idx + 1).min(plane.len() - idx - 1);
+
+        for offset in 0..reach {
+            let left = idx - offset;
+            let right = idx + 1 + offset;
 
-        loop {
             if plane[left] != plane[right] {
-                break;
-            } else {
-                if left == 0 || right == plane.len() - 1 {
-                    found = true;
+                differences += plane[left].differences(&plane[right]);
+                smudged.push((left, right));
+
+                if differences > k {
                     break;
-                } else {
-                    left -= 1;
-                    right += 1;
                 }
             }
         }
 
-        if found {
-            return Some(idx);
+        if differences == k {
+            let smudges = smudged
+                .iter()
+                .flat_map(|(left, right)| {
+                    plane[*left].differing_cells(&plane[*right]).into_iter().map(|cell| (*left, cell))
+                }).collect();
+
+            return Some((idx, smudges));
         }
     }
 
     return None;
 }
 
-fn off_by_one(left: &Vec<bool>, right: &Vec<bool>) -> bool {
-    left.iter().zip(right).filter(|(l, r)| l != r).count() == 1
+fn find_reflection(mirror: &Mirror, k: usize) -> Option<Reflection> {
+    // Horizontal seams take priority over vertical seams.
+
+    if let Some((seam, smudges)) = mirror.horizontal.search(k) {
+        let smudges = smudges.into_iter().map(|(y, x)| (x, y)).collect();
+
+        return Some(Reflection { seam, orientation: Orientation::Horizontal, smudges });
+    }
+
+    if let Some((seam, smudges)) = mirror.vertical.search(k) {
+        return Some(Reflection { seam, orientation: Orientation::Vertical, smudges });
+    }
+
+    return None;
 }
 
-fn search_for_smudge(plane: &Vec<Vec<bool>>) -> Option<usize> {
-    // Find the first idx which can reduce into a reflection, including a smudge.
-    // A smudge is an off-by-one error, where a single value is wrong.
+fn locate_mirrors(k: usize) -> usize {
+    // Sum the scores of every mirror, allowing exactly k smudges in each.
 
-    for idx in 0..(plane.len() - 1) {
-        let mut left = idx;
-        let mut right = left + 1;
-        let mut found = false;
-        let mut smudge_used = false;
+    if let Some(mirrors) = fs::read_to_string("data/13.input").ok() {
+        let mirrors = get_mirrors(mirrors);
 
-        loop {
-            if plane[left] != plane[right] {
-                if !smudge_used && off_by_one(&plane[left], &plane[right]) {
-                    // Bypass the break if smudge hasn't been used.
-                    smudge_used = true;
-                } else {
-                    break;
-                }
-            }
+        return mirrors
+            .iter()
+            .filter_map(|mirror| find_reflection(mirror, k))
+            .map(|reflection| reflection.score())
+            .sum::<usize>()
+    }
 
-            if left == 0 || right == plane.len() - 1 {
-                found = true;
-                break;
-            } else {
-                left -= 1;
-                right += 1;
-            }
+    panic!("file not found")
+}
+
+fn generate_patterns(width: usize, height: usize, count: usize, k: usize) -> Vec<Vec<Vec<bool>>> {
+    // Random patterns, each reflected across a random seam, then smudged k times.
+    // Uses xorshift64 with a fixed seed, so every run times the same patterns.
+
+    let mut state: u64 = 0x9e37_79b9_7f4a_7c15;
+    let mut random = |bound: usize| {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+
+        (state % bound as u64) as usize
+    };
+
+    return (0..count).map(|_| {
+        let mut pattern = (0..height)
+            .map(|_| (0..width).map(|_| random(2) == 1).collect())
+            .collect::<Vec<Vec<bool>>>();
+
+        let seam = random(height - 1);
+
+        for offset in 0..(seam + 1).min(height - seam - 1) {
+            pattern[seam + 1 + offset] = pattern[seam - offset].clone();
         }
 
-        // Ensure we used a smudge.
-        if found && smudge_used {
-            return Some(idx);
+        for _ in 0..k {
+            let (x, y) = (random(width), random(height));
+            pattern[y][x] = !pattern[y][x];
         }
+
+        pattern
+    }).collect();
+}
+
+fn benchmark(width: usize, height: usize, count: usize) {
+    // Time the one smudge search over generated patterns, first with a bool per cell,
+    // then with the bitmask lines, and check both find the same reflections.
+
+    if width < 2 || height < 2 {
+        panic!("patterns need at least two rows and columns");
     }
 
-    return None;
+    let patterns = generate_patterns(width, height, count, 1);
+
+    let time = |encode: fn(Vec<Vec<bool>>) -> Plane| {
+        let mirrors = patterns.iter().map(|pattern| build_mirror(pattern.clone(), encode)).collect::<Vec<Mirror>>();
+        let start = Instant::now();
+        let score = mirrors.iter().filter_map(|mirror| find_reflection(mirror, 1)).map(|reflection| reflection.score()).sum::<usize>();
+
+        (score, start.elapsed())
+    };
+
+    let (cells_score, cells_time) = time(Plane::Cells);
+    let (masks_score, masks_time) = time(Plane::encode);
+
+    println!("{count} patterns of {width}x{height}");
+    println!("bools:     {cells_time:?} (score {cells_score})");
+    println!("bitmasks:  {masks_time:?} (score {masks_score})");
+    println!("speedup:   {:.1}x", cells_time.as_secs_f64() / masks_time.as_secs_f64().max(f64::EPSILON));
+
+    if cells_score != masks_score {
+        panic!("bitmask search disagrees with the bool search");
+    }
 }
 
-fn locate_mirrors() -> usize {
-    if let Some(mirrors) = fs::read_to_string("data/13.input").ok() {
-        let mirrors = get_mirrors(mirrors);
+fn corrected_pattern(mirror: &Mirror, reflection: &Reflection) -> String {
+    // Draw the pattern with every smudge cleaned up, and the seam drawn in.
+
+    let mut pattern = mirror.pattern.clone();
+    reflection.smudges.iter().for_each(|(x, y)| pattern[*y][*x] = !pattern[*y][*x]);
+
+    return pattern
+        .iter()
+        .enumerate()
+        .map(|(y, row)| {
+            let mut line = row
+                .iter()
+                .enumerate()
+                .map(|(x, cell)| {
+                    let c = if *cell { "#" } else { "." };
 
-        return mirrors.iter().map(|mirror| {
-            if let Some(seam) = search_for_seam(&mirror.horizontal) {
-                // 100 * idx for horizontal mirrors.
-                return (seam + 1) * 100;
-            } else {
-                if let Some(seam) = search_for_seam(&mirror.vertical) {
-                    // 1 * idx for vertical mirrors.
-                    return seam + 1;
+                    match reflection.orientation {
+                        Orientation::Vertical if x == reflection.seam => format!("{c}|"),
+                        _ => c.to_string()
+                    }
+                }).collect::<String>();
+
+            line.push('\n');
+
+            if let Orientation::Horizontal = reflection.orientation {
+                if y == reflection.seam {
+                    line.push_str(&"-".repeat(row.len()));
+                    line.push('\n');
                 }
             }
 
-            return 0;
-        }).sum::<usize>()
-    }
-
-    panic!("file not found")
+            line
+        }).collect();
 }
 
-fn locate_smudged_mirrors() -> usize {
-    // Instead of finding the reflective point of a mirror, find the first off-by-one error.
+fn explain_mirrors(k: usize) {
+    // Report each mirror's seam and smudges, then print it corrected.
 
     if let Some(mirrors) = fs::read_to_string("data/13.input").ok() {
-        let mirrors = get_mirrors(mirrors);
+        get_mirrors(mirrors).iter().enumerate().for_each(|(idx, mirror)| {
+            match find_reflection(mirror, k) {
+                Some(reflection) => {
+                    let orientation = match reflection.orientation {
+                        Orientation::Horizontal => "horizontal seam below row",
+                        Orientation::Vertical => "vertical seam right of column"
+                    };
+                    let smudges = reflection.smudges
+                        .iter()
+                        .map(|(x, y)| format!("{x},{y}"))
+                        .collect::<Vec<String>>()
+                        .join(" ");
 
-        return mirrors.iter().map(|mirror| {
-            if let Some(seam) = search_for_smudge(&mirror.horizontal) {
-                // 100 * idx for horizontal mirrors.
-                return (seam + 1) * 100;
-            } else {
-                if let Some(seam) = search_for_smudge(&mirror.vertical) {
-                    // 1 * idx for vertical mirrors.
-                    return seam + 1;
-                }
+                    println!("pattern {}: {orientation} {}, smudges: {}", idx + 1, reflection.seam + 1,
+                        if smudges.is_empty() { "none" } else { &smudges });
+                    println!("{}", corrected_pattern(mirror, &reflection));
+                },
+                None => println!("pattern {}: no reflection with {k} smudges\n", idx + 1)
             }
+        });
 
-            return 0;
-        }).sum::<usize>()
+        return;
     }
 
-    panic!("file not found");
-
+    panic!("file not found")
 }
 
 fn main() {
-    println!("part one: {}", locate_mirrors());
-    println!("part two: {}", locate_smudged_mirrors());
+    // `cargo run --bin day13 -- <k>` allows exactly k smudges per mirror,
+    // `explain [k]` prints each corrected pattern. k defaults to 1.
+    // `bench [width] [height] [count]` times bitmask lines against bools, best with `--release`.
+
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+    let smudges = |arg: &str| match arg.parse::<usize>() {
+        Ok(k) => k,
+        Err(_) => panic!("improper smudge count: {arg}")
+    };
+
+    match args.iter().map(|arg| arg.as_str()).collect::<Vec<&str>>()[..] {
+        ["bench", ref sizes @ ..] => {
+            let size = |idx: usize, default: usize| sizes.get(idx).map_or(default, |size| match size.parse::<usize>() {
+                Ok(size) => size,
+                Err(_) => panic!("improper size: {size}")
+            });
+
+            benchmark(size(0, 100), size(1, 100), size(2, 1000));
+        },
+        ["explain"] => explain_mirrors(1),
+        ["explain", k] => explain_mirrors(smudges(k)),
+        [k] => println!("{k} smudges: {}", locate_mirrors(smudges(k))),
+        _ => {
+            println!("part one: {}", locate_mirrors(0));
+            println!("part two: {}", locate_mirrors(1));
+        }
+    }
 }
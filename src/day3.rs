@@ -101,33 +101,336 @@ fn sum_of_true_parts() -> usize {
 
 }
 
+#[derive(Clone, Copy)]
+enum Combination {
+    Product,
+    Sum
+}
+
+struct GearRule {
+    symbols: Vec<char>,
+    arity: usize,
+    combination: Combination
+}
+
+impl GearRule {
+    fn standard() -> GearRule {
+        // A `*` with exactly two numbers near it, multiplied together.
+
+        return GearRule {
+            symbols: vec!['*'],
+            arity: 2,
+            combination: Combination::Product
+        };
+    }
+
+    fn from_str(rule: &str) -> Option<GearRule> {
+        // Rules are written as `<symbols>:<arity>:<product|sum>`, e.g. `*#:3:sum`.
+
+        let mut parts = rule.split(':');
+
+        let symbols = parts.next()?.chars().collect::<Vec<char>>();
+        let arity = parts.next()?.parse::<usize>().ok()?;
+        let combination = match parts.next()? {
+            "product" => Combination::Product,
+            "sum" => Combination::Sum,
+            _ => return None
+        };
+
+        if symbols.is_empty() || parts.next().is_some() {
+            return None;
+        }
+
+        return Some(GearRule { symbols, arity, combination });
+    }
+}
+
+fn gear_value(rule: &GearRule, symbol: &Symbol, numbers: &[Number]) -> Option<usize> {
+    // A gear is one of the rule's symbols with exactly `arity` numbers near it,
+    // its ratio is those numbers combined by the rule's combination.
+
+    if !rule.symbols.contains(&symbol.value) {
+        return None;
+    }
+
+    // Find each number pertinent to our gear.
+    let cogs = numbers
+        .iter()
+        .filter(|number| symbol_touches_number(symbol, number))
+        .map(|number| number.value)
+        .collect::<Vec<usize>>();
+
+    if cogs.len() == rule.arity {
+        return match rule.combination {
+            Combination::Product => Some(cogs.iter().product::<usize>()),
+            Combination::Sum => Some(cogs.iter().sum::<usize>())
+        };
+    } else {
+        None
+    }
+}
+
+fn sum_of_gears(rule: &GearRule) -> usize {
+    // Find the sum of all gears matching the given rule.
+
+    let (symbols, numbers) = parse_numbers_and_symbols();
+
+    return symbols
+        .iter()
+        .filter_map(|symbol| gear_value(rule, symbol, &numbers))
+        .sum();
+}
+
 fn sum_of_gear_ratios() -> usize {
     // Find the sum of all gear ratios in the input.
     // A gear is a `*` symbol with exactly two numbers near it,
     // its ratio is the product of those two numbers.
 
-    let (symbols, numbers) = parse_numbers_and_symbols();
+    return sum_of_gears(&GearRule::standard());
+}
+
+fn adjacency_edges(symbols: &[Symbol], numbers: &[Number]) -> Vec<(usize, usize)> {
+    // Every (symbol index, number index) pair where the symbol touches the number.
 
     return symbols
         .iter()
-        .filter(|symbol| symbol.value == '*')
-        .filter_map(|symbol| {
-            // Find each number pertinent to our gear.
-
-            let cogs = numbers
+        .enumerate()
+        .flat_map(|(s, symbol)| {
+            numbers
                 .iter()
-                .filter(|number| symbol_touches_number(symbol, number))
-                .collect::<Vec<&Number>>();
+                .enumerate()
+                .filter(move |(_, number)| symbol_touches_number(symbol, number))
+                .map(move |(n, _)| (s, n))
+        }).collect();
+}
 
-            if cogs.len() == 2 {
-                return Some(cogs[0].value * cogs[1].value);
-            } else {
-                None
-            }
-        }).sum();
+fn adjacency_dot() -> String {
+    // Export the symbol-number adjacency graph as Graphviz DOT.
+    // Symbols are boxes, numbers are ellipses; numbers touching nothing are left unconnected.
+
+    let (symbols, numbers) = parse_numbers_and_symbols();
+    let mut dot = String::from("graph schematic {\n");
+
+    symbols.iter().enumerate().for_each(|(idx, symbol)| {
+        let label = if symbol.value == '"' || symbol.value == '\\' {
+            format!("\\{}", symbol.value)
+        } else {
+            symbol.value.to_string()
+        };
+
+        dot.push_str(&format!(
+            "    s{idx} [shape=box, label=\"{label} ({},{})\"];\n",
+            symbol.x, symbol.y
+        ));
+    });
+
+    numbers.iter().enumerate().for_each(|(idx, number)| {
+        dot.push_str(&format!(
+            "    n{idx} [label=\"{} ({},{})\"];\n",
+            number.value, number.start, number.y
+        ));
+    });
+
+    adjacency_edges(&symbols, &numbers).iter().for_each(|(s, n)| {
+        dot.push_str(&format!("    s{s} -- n{n};\n"));
+    });
+
+    dot.push_str("}\n");
+
+    return dot;
+}
+
+fn adjacency_json() -> String {
+    // Export the symbol-number adjacency graph as JSON.
+    // Edges refer to symbols and numbers by their index in the respective arrays.
+
+    let (symbols, numbers) = parse_numbers_and_symbols();
+
+    let symbols_json = symbols
+        .iter()
+        .map(|symbol| {
+            let value = match symbol.value {
+                '"' => "\\\"".to_string(),
+                '\\' => "\\\\".to_string(),
+                c => c.to_string()
+            };
+
+            format!("{{\"value\":\"{value}\",\"x\":{},\"y\":{}}}", symbol.x, symbol.y)
+        })
+        .collect::<Vec<String>>()
+        .join(",");
+
+    let numbers_json = numbers
+        .iter()
+        .map(|number| {
+            format!(
+                "{{\"value\":{},\"y\":{},\"start\":{},\"end\":{}}}",
+                number.value, number.y, number.start, number.end
+            )
+        })
+        .collect::<Vec<String>>()
+        .join(",");
+
+    let edges_json = adjacency_edges(&symbols, &numbers)
+        .iter()
+        .map(|(s, n)| format!("{{\"symbol\":{s},\"number\":{n}}}"))
+        .collect::<Vec<String>>()
+        .join(",");
+
+    return format!(
+        "{{\"symbols\":[{symbols_json}],\"numbers\":[{numbers_json}],\"edges\":[{edges_json}]}}"
+    );
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    Terminal,
+    Html
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Cell {
+    Blank,
+    Part,
+    Unattached,
+    Symbol,
+    Gear
+}
+
+fn paint(format: Format, cell: Cell, text: &str) -> String {
+    // Wrap some text in the colouring for its kind of cell.
+
+    match format {
+        Format::Terminal => {
+            let code = match cell {
+                Cell::Blank => return text.to_string(),
+                Cell::Part => "32",
+                Cell::Unattached => "31",
+                Cell::Symbol => "36",
+                Cell::Gear => "1;33"
+            };
+
+            return format!("\x1b[{code}m{text}\x1b[0m");
+        },
+        Format::Html => {
+            let text = text
+                .replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;");
+
+            let class = match cell {
+                Cell::Blank => return text,
+                Cell::Part => "part",
+                Cell::Unattached => "unattached",
+                Cell::Symbol => "symbol",
+                Cell::Gear => "gear"
+            };
+
+            return format!("<span class=\"{class}\">{text}</span>");
+        }
+    }
+}
+
+fn render_schematic(format: Format) -> String {
+    // Draw the schematic with part numbers, unattached numbers, symbols and gears coloured in.
+    // Each gear's ratio is listed at the end of the line it sits on.
+
+    let input = fs::read_to_string("data/3.input").unwrap();
+    let (symbols, numbers) = parse_numbers_and_symbols();
+    let rule = GearRule::standard();
+
+    let mut grid = input
+        .lines()
+        .map(|line| line.chars().map(|c| (c, Cell::Blank)).collect::<Vec<(char, Cell)>>())
+        .collect::<Vec<Vec<(char, Cell)>>>();
+
+    let mut ratios: Vec<Vec<(usize, usize)>> = vec![vec![]; grid.len()];
+
+    numbers.iter().for_each(|number| {
+        let cell = if symbols.iter().any(|symbol| symbol_touches_number(symbol, number)) {
+            Cell::Part
+        } else {
+            Cell::Unattached
+        };
+
+        (number.start..=number.end).for_each(|x| grid[number.y][x].1 = cell);
+    });
+
+    symbols.iter().for_each(|symbol| {
+        if let Some(ratio) = gear_value(&rule, symbol, &numbers) {
+            grid[symbol.y][symbol.x].1 = Cell::Gear;
+            ratios[symbol.y].push((symbol.x, ratio));
+        } else {
+            grid[symbol.y][symbol.x].1 = Cell::Symbol;
+        }
+    });
+
+    let body = grid
+        .iter()
+        .zip(ratios)
+        .map(|(line, ratios)| {
+            // Group runs of the same kind of cell, so each number is coloured as one span.
+
+            let mut rendered = String::new();
+            let mut run = String::new();
+            let mut current = Cell::Blank;
+
+            line.iter().for_each(|(c, cell)| {
+                if *cell != current && !run.is_empty() {
+                    rendered.push_str(&paint(format, current, &run));
+                    run.clear();
+                }
+
+                current = *cell;
+                run.push(*c);
+            });
+
+            rendered.push_str(&paint(format, current, &run));
+
+            ratios.iter().for_each(|(x, ratio)| {
+                rendered.push_str(&format!("  {}", paint(format, Cell::Gear, &format!("x{x}={ratio}"))));
+            });
+
+            rendered
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    return match format {
+        Format::Terminal => format!("{body}\n"),
+        Format::Html => format!(
+            "<style>\n\
+            .part {{ color: green; }}\n\
+            .unattached {{ color: red; }}\n\
+            .symbol {{ color: teal; }}\n\
+            .gear {{ color: goldenrod; font-weight: bold; }}\n\
+            </style>\n<pre>\n{body}\n</pre>\n"
+        )
+    };
 }
 
 fn main() {
-    println!("part one: {}", sum_of_true_parts());
-    println!("part two: {}", sum_of_gear_ratios());
+    // `cargo run --bin day3 -- dot|json` exports the adjacency graph,
+    // `cargo run --bin day3 -- gears <symbols>:<arity>:<product|sum>` applies a custom gear rule,
+    // `cargo run --bin day3 -- render [html]` draws the annotated schematic.
+
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+
+    match args.iter().map(|arg| arg.as_str()).collect::<Vec<&str>>()[..] {
+        ["dot"] => print!("{}", adjacency_dot()),
+        ["json"] => println!("{}", adjacency_json()),
+        ["render"] => print!("{}", render_schematic(Format::Terminal)),
+        ["render", "html"] => print!("{}", render_schematic(Format::Html)),
+        ["gears", rule] => {
+            if let Some(rule) = GearRule::from_str(rule) {
+                println!("gears: {}", sum_of_gears(&rule));
+            } else {
+                panic!("improper gear rule: {rule}");
+            }
+        },
+        _ => {
+            println!("part one: {}", sum_of_true_parts());
+            println!("part two: {}", sum_of_gear_ratios());
+        }
+    }
 }
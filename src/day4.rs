@@ -40,31 +40,106 @@ fn total_winnings() -> usize {
     panic!("file not found")
 }
 
-fn scratchcard_quantities() -> usize {
+#[derive(Clone, Copy)]
+enum Overflow {
+    Clip,
+    Error
+}
+
+struct Ledger {
+    card: usize,
+    matches: usize,
+    copies: usize,
+    sources: Vec<(usize, usize)>
+}
+
+fn card_ledger(overflow: Overflow) -> Result<Vec<Ledger>, String> {
+    // For each card, track how many matches it had and how many copies it received from which
+    // earlier cards.
+    //
+    // A card winning past the end of the table either has its extra copies dropped (`Clip`),
+    // or is reported as an error with its card number (`Error`).
+
     if let Some(input) = fs::read_to_string("data/4.input").ok() {
         let lines = input.lines().collect::<Vec<&str>>();
 
-        // Winning n gives you an extra card for the next n cards.
-        // This compounds strongly so we need to keep adding extra cards consistently.
-        //
-        // We do this by tracking the quantities of each card, and adding to it as we go.
-        let mut quantities = vec![1; lines.len()];
+        let mut ledger = lines
+            .iter()
+            .enumerate()
+            .map(|(idx, line)| Ledger { card: idx + 1, matches: wins(line), copies: 0, sources: vec![] })
+            .collect::<Vec<Ledger>>();
 
-        lines.iter().enumerate().for_each(|(idx, line)| {
-            let wins = wins(line);
+        for idx in 0..ledger.len() {
+            let (matches, held) = (ledger[idx].matches, ledger[idx].copies + 1);
 
-            for i in idx+1..=idx+wins {
-                quantities[i] += quantities[idx];
+            if idx + matches >= ledger.len() {
+                if let Overflow::Error = overflow {
+                    return Err(format!(
+                        "card {} wins {} cards, but only {} follow it",
+                        ledger[idx].card,
+                        matches,
+                        ledger.len() - idx - 1
+                    ));
+                }
             }
-        });
 
-        return quantities.iter().sum();
+            for i in idx+1..=(idx+matches).min(ledger.len() - 1) {
+                ledger[i].copies += held;
+                ledger[i].sources.push((idx + 1, held));
+            }
+        }
+
+        return Ok(ledger);
     }
 
     panic!("file not found")
 }
 
+fn scratchcard_quantities() -> usize {
+    // Winning n gives you an extra card for the next n cards.
+    // This compounds strongly so we need to keep adding extra cards consistently.
+    //
+    // We do this by tracking the quantities of each card, and adding to it as we go.
+
+    match card_ledger(Overflow::Clip) {
+        Ok(ledger) => ledger.iter().map(|entry| entry.copies + 1).sum(),
+        Err(e) => panic!("{e}")
+    }
+}
+
+fn print_ledger(overflow: Overflow) {
+    match card_ledger(overflow) {
+        Ok(ledger) => ledger.iter().for_each(|entry| {
+            let sources = entry.sources
+                .iter()
+                .map(|(card, copies)| format!("{copies} from card {card}"))
+                .collect::<Vec<String>>()
+                .join(", ");
+
+            println!(
+                "card {}: {} matches, {} copies received{}",
+                entry.card,
+                entry.matches,
+                entry.copies,
+                if sources.is_empty() { String::new() } else { format!(" ({sources})") }
+            );
+        }),
+        Err(e) => println!("error: {e}")
+    }
+}
+
 fn main() {
-    println!("part one: {}", total_winnings());
-    println!("part two: {}", scratchcard_quantities());
+    // `cargo run --bin day4 -- ledger [strict]` prints the per-card copy ledger,
+    // with `strict` erroring on cards that win past the end of the table instead of clipping.
+
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+
+    match args.iter().map(|arg| arg.as_str()).collect::<Vec<&str>>()[..] {
+        ["ledger"] => print_ledger(Overflow::Clip),
+        ["ledger", "strict"] => print_ledger(Overflow::Error),
+        _ => {
+            println!("part one: {}", total_winnings());
+            println!("part two: {}", scratchcard_quantities());
+        }
+    }
 }
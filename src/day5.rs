@@ -1,5 +1,7 @@
 use std::fs;
 
+type Lowest = Option<(isize, isize)>;
+
 #[derive(Clone)]
 struct Connection {
     start: isize,
@@ -56,12 +58,157 @@ fn get_seeds(seeds: &str) -> Vec<isize> {
         .collect();
 }
 
-fn find_values(seeds: Vec<isize>, almanac: Vec<Map>, start: String, end: String) -> Vec<isize> {
-    if start == end {
-        return seeds;
+struct Validation {
+    errors: Vec<String>,
+    warnings: Vec<String>
+}
+
+fn validate_almanac(almanac: &[Map], start: &str, end: &str) -> Validation {
+    // Check the almanac can sensibly convert `start` into `end` before solving.
+    //
+    // Errors: overlapping source ranges within a map, categories with several outgoing maps,
+    // cycles in the from/to chain, and missing links between `start` and `end`.
+    // Warnings: categories off the chain through `start`, neither feeding into it nor reached
+    // from it.
+
+    let mut errors = vec![];
+    let mut warnings = vec![];
+
+    for map in almanac.iter() {
+        let mut connections = map.map.clone();
+        connections.sort_by_key(|conn| conn.start);
+
+        // Compare each range with whichever earlier one reaches furthest, so a long range is
+        // reported against every later range it covers.
+        let mut furthest: Option<&Connection> = None;
+
+        for conn in connections.iter() {
+            if let Some(earlier) = furthest {
+                if conn.start <= earlier.end {
+                    errors.push(format!(
+                        "{}-to-{} map: source ranges {}..={} and {}..={} overlap",
+                        map.from, map.to, earlier.start, earlier.end, conn.start, conn.end
+                    ));
+                }
+            }
+
+            if furthest.is_none_or(|earlier| conn.end > earlier.end) {
+                furthest = Some(conn);
+            }
+        }
+    }
+
+    let mut categories = almanac
+        .iter()
+        .flat_map(|map| [map.from.clone(), map.to.clone()])
+        .collect::<Vec<String>>();
+
+    categories.sort();
+    categories.dedup();
+
+    for category in categories.iter() {
+        if almanac.iter().filter(|map| &map.from == category).count() > 1 {
+            errors.push(format!("multiple maps from {category}"));
+        }
+    }
+
+    // Follow the chain from every category, reporting each cycle once, starting from its
+    // alphabetically first category.
+    for category in categories.iter() {
+        let mut path = vec![category.clone()];
+
+        while let Some(map) = almanac.iter().find(|map| Some(&map.from) == path.last()) {
+            if let Some(idx) = path.iter().position(|c| c == &map.to) {
+                let cycle = &path[idx..];
+
+                if idx == 0 && cycle.iter().all(|c| c >= category) {
+                    errors.push(format!("cycle: {} -> {category}", cycle.join(" -> ")));
+                }
+
+                break;
+            }
+
+            path.push(map.to.clone());
+        }
+    }
+
+    if !categories.iter().any(|c| c == start) {
+        errors.push(format!("unknown category {start}"));
+    }
+
+    if !categories.iter().any(|c| c == end) {
+        errors.push(format!("unknown category {end}"));
+    }
+
+    // Walk from `start`, looking for the link which goes missing before `end`.
+    let mut reachable = vec![start.to_string()];
+    let mut cycled = false;
+
+    while let Some(map) = almanac.iter().find(|map| Some(&map.from) == reachable.last()) {
+        if reachable.contains(&map.to) {
+            cycled = true;
+            break;
+        }
+
+        reachable.push(map.to.clone());
+    }
+
+    if !reachable.iter().any(|c| c == end) && categories.iter().any(|c| c == start) {
+        if cycled {
+            errors.push(format!("{end} can't be reached from {start}, the chain cycles first"));
+        } else {
+            errors.push(format!(
+                "no map from {}, so {end} can't be reached from {start}",
+                reachable.last().unwrap()
+            ));
+        }
+    }
+
+    // Walk backwards from `start` too, so the categories upstream of it aren't reported.
+    let mut upstream = vec![start.to_string()];
+    let mut queue = vec![start.to_string()];
+
+    while let Some(category) = queue.pop() {
+        for map in almanac.iter().filter(|map| map.to == category) {
+            if !upstream.contains(&map.from) {
+                upstream.push(map.from.clone());
+                queue.push(map.from.clone());
+            }
+        }
+    }
+
+    categories
+        .iter()
+        .filter(|category| !reachable.contains(category) && !upstream.contains(category))
+        .for_each(|category| warnings.push(format!("{category} is off the chain through {start}")));
+
+    return Validation { errors, warnings };
+}
+
+fn map_chain<'a>(almanac: &'a [Map], start: &str, end: &str) -> Vec<&'a Map> {
+    // The maps to apply, in order, to get from `start` to `end`.
+
+    let mut chain = vec![];
+    let mut category = start;
+
+    while category != end {
+        if let Some(map) = almanac.iter().find(|map| map.from == category) {
+            if chain.len() == almanac.len() {
+                panic!("Cycle through {category}");
+            }
+
+            chain.push(map);
+            category = &map.to;
+        } else {
+            panic!("No map from {category}");
+        }
     }
 
-    if let Some(map) = almanac.clone().iter().find(|map| map.from == start) {
+    return chain;
+}
+
+fn find_values(seeds: Vec<isize>, chain: &[&Map]) -> Vec<isize> {
+    if let Some((map, rest)) = chain.split_first() {
         let new_seeds = seeds.iter().map(|seed| {
             for connection in map.map.iter() {
                 if (connection.start..=connection.end).contains(seed) {
@@ -71,24 +218,22 @@ fn find_values(seeds: Vec<isize>, almanac: Vec<Map>, start: String, end: String)
             return *seed;
         }).collect::<Vec<isize>>();
 
-        return find_values(new_seeds, almanac, map.to.clone(), end);
+        return find_values(new_seeds, rest);
     }
 
-    panic!("No map from {start}");
+    return seeds;
 }
 
-fn get_smallest_seed() -> isize {
+fn get_smallest_seed(start: &str, end: &str) -> isize {
     if let Some(almanac) = fs::read_to_string("data/5.input").ok() {
         if let Some((seeds, almanac)) = almanac.split_once("\n\n") {
             let seeds = get_seeds(seeds);
             let almanac = get_almanac(almanac);
 
-            return find_values(
-                seeds,
-                almanac,
-                "seed".to_string(),
-                "location".to_string()
-            ).into_iter().min().unwrap_or(0);
+            return find_values(seeds, &map_chain(&almanac, start, end))
+                .into_iter()
+                .min()
+                .unwrap_or(0);
         }
     }
 
@@ -112,10 +257,8 @@ fn get_seed_ranges(seeds: &str) -> Vec<(isize, isize)> {
         }).collect();
 }
 
-fn find_ranges(seeds: Vec<(isize, isize)>, almanac: Vec<Map>, start: String, end: String) -> Vec<(isize, isize)> {
-    if start == end { return seeds; }
-
-    if let Some(map) = almanac.clone().iter().find(|map| map.from == start) {
+fn find_ranges(seeds: Vec<(isize, isize)>, chain: &[&Map]) -> Vec<(isize, isize)> {
+    if let Some((map, rest)) = chain.split_first() {
         let new_seeds = seeds.iter().map(|seed_range| {
             // Items are only mapped once, this ensures that.
             let mut found_ranges = vec![];
@@ -173,13 +316,13 @@ fn find_ranges(seeds: Vec<(isize, isize)>, almanac: Vec<Map>, start: String, end
 
         let new_seeds: Vec<(isize, isize)> = new_seeds.into_iter().flat_map(|v| v).collect();
 
-        return find_ranges(new_seeds, almanac, map.to.clone(), end);
+        return find_ranges(new_seeds, rest);
     }
 
-    panic!("No map from {start}");
+    return seeds;
 }
 
-fn get_smallest_seed_from_range() -> isize {
+fn get_smallest_seed_from_range(start: &str, end: &str) -> isize {
     if let Some(almanac) = fs::read_to_string("data/5.input").ok() {
         if let Some((seeds, almanac)) = almanac.split_once("\n\n") {
             let seeds = get_seed_ranges(seeds);
@@ -187,12 +330,7 @@ fn get_smallest_seed_from_range() -> isize {
 
             // Instead of checking individual elements, we check ranges of elements, splitting
             // ranges apart when need be.
-            let ranges = find_ranges(
-                seeds,
-                almanac,
-                "seed".to_string(),
-                "location".to_string()
-            );
+            let ranges = find_ranges(seeds, &map_chain(&almanac, start, end));
 
             // Smallest start to a range.
             return ranges
@@ -206,7 +344,282 @@ fn get_smallest_seed_from_range() -> isize {
     panic!("file not found")
 }
 
+// The piecewise functions cover this domain, leaving headroom so offsets can't overflow.
+const DOMAIN: (isize, isize) = (isize::MIN / 4, isize::MAX / 4);
+
+#[derive(Clone)]
+struct Piecewise {
+    // Sorted, contiguous segments covering `DOMAIN`, each mapping `x` to `x + offset`.
+    segments: Vec<Connection>
+}
+
+impl Piecewise {
+    fn from_segments(mut segments: Vec<Connection>) -> Piecewise {
+        // Sort the segments and merge neighbours which share an offset.
+
+        segments.sort_by_key(|segment| segment.start);
+
+        let mut merged: Vec<Connection> = vec![];
+
+        for segment in segments {
+            if let Some(last) = merged.last_mut() {
+                if last.offset == segment.offset && last.end + 1 == segment.start {
+                    last.end = segment.end;
+                    continue;
+                }
+            }
+
+            merged.push(segment);
+        }
+
+        return Piecewise { segments: merged };
+    }
+
+    fn from_map(map: &Map) -> Piecewise {
+        // Cut the domain at every connection boundary, then give each piece the offset of the
+        // first connection containing it, the same one `find_values` would pick.
+
+        let mut cuts = vec![DOMAIN.0, DOMAIN.1 + 1];
+
+        map.map.iter().for_each(|conn| {
+            cuts.push(conn.start.clamp(DOMAIN.0, DOMAIN.1 + 1));
+            cuts.push((conn.end + 1).clamp(DOMAIN.0, DOMAIN.1 + 1));
+        });
+
+        cuts.sort();
+        cuts.dedup();
+
+        let segments = cuts
+            .windows(2)
+            .map(|cut| {
+                let offset = map.map
+                    .iter()
+                    .find(|conn| conn.start <= cut[0] && cut[0] <= conn.end)
+                    .map(|conn| conn.offset)
+                    .unwrap_or(0);
+
+                return Connection { start: cut[0], end: cut[1] - 1, offset };
+            }).collect();
+
+        return Piecewise::from_segments(segments);
+    }
+
+    fn eval(&self, x: isize) -> isize {
+        // Binary search for the segment containing `x`.
+
+        let idx = self.segments.partition_point(|segment| segment.end < x);
+
+        return match self.segments.get(idx) {
+            Some(segment) if segment.start <= x => x + segment.offset,
+            _ => x
+        };
+    }
+
+    fn then(&self, next: &Piecewise) -> Piecewise {
+        // Compose two functions, applying `self` and then `next`.
+        // Each segment's image is split wherever it crosses a segment boundary of `next`.
+
+        let mut segments = vec![];
+
+        for segment in self.segments.iter() {
+            let (mut lo, hi) = (segment.start + segment.offset, segment.end + segment.offset);
+            let mut idx = next.segments.partition_point(|n| n.end < lo);
+
+            while lo <= hi {
+                let (end, offset) = match next.segments.get(idx) {
+                    Some(n) if n.start <= lo => (n.end.min(hi), n.offset),
+                    Some(n) => ((n.start - 1).min(hi), 0),
+                    None => (hi, 0)
+                };
+
+                segments.push(Connection {
+                    start: lo - segment.offset,
+                    end: end - segment.offset,
+                    offset: segment.offset + offset
+                });
+
+                lo = end + 1;
+                idx += 1;
+            }
+        }
+
+        return Piecewise::from_segments(segments);
+    }
+
+    fn invert(&self) -> Vec<Connection> {
+        // The inverse, as pieces sorted by their image.
+        // Maps aren't necessarily injective, so pieces may overlap: a value can have several
+        // preimages.
+
+        let mut inverse = self.segments
+            .iter()
+            .map(|segment| Connection {
+                start: segment.start + segment.offset,
+                end: segment.end + segment.offset,
+                offset: -segment.offset
+            }).collect::<Vec<Connection>>();
+
+        inverse.sort_by_key(|piece| piece.start);
+
+        return inverse;
+    }
+}
+
+fn compose_chain(chain: &[&Map]) -> Piecewise {
+    // Fold every map in a chain into a single function.
+
+    let identity = Piecewise::from_segments(vec![Connection { start: DOMAIN.0, end: DOMAIN.1, offset: 0 }]);
+
+    return chain
+        .iter()
+        .fold(identity, |function, map| function.then(&Piecewise::from_map(map)));
+}
+
+fn lowest_location(function: &Piecewise, seeds: &[(isize, isize)]) -> Lowest {
+    // Walk the inverse in order of location, pulling each piece back to the seeds which produce
+    // it. The first seed range hit gives the lowest location, along with the seed that produced it.
+
+    let mut best: Option<(isize, isize)> = None;
+
+    for piece in function.invert() {
+        if best.is_some_and(|(location, _)| location < piece.start) {
+            break;
+        }
+
+        let (pre_lo, pre_hi) = (piece.start + piece.offset, piece.end + piece.offset);
+
+        for (lo, hi) in seeds.iter() {
+            if *lo <= pre_hi && pre_lo <= *hi {
+                let seed = pre_lo.max(*lo);
+                let location = seed - piece.offset;
+
+                if best.is_none_or(|(best_location, _)| location < best_location) {
+                    best = Some((location, seed));
+                }
+            }
+        }
+    }
+
+    return best;
+}
+
+fn get_smallest_seeds_composed(start: &str, end: &str) -> (Lowest, Lowest) {
+    // Both parts, answered through the composed `start`-to-`end` function.
+    // Returns `(location, seed)` for the individual seeds and for the seed ranges, if there are any.
+
+    if let Some(almanac) = fs::read_to_string("data/5.input").ok() {
+        if let Some((seeds, almanac)) = almanac.split_once("\n\n") {
+            let almanac = get_almanac(almanac);
+            let function = compose_chain(&map_chain(&almanac, start, end));
+
+            // Individual seeds can just be evaluated, ranges need the inverse.
+            return (
+                get_seeds(seeds).into_iter().map(|seed| (function.eval(seed), seed)).min(),
+                lowest_location(&function, &get_seed_ranges(seeds))
+            );
+        }
+    }
+
+    panic!("file not found")
+}
+
+fn validate(start: &str, end: &str) -> bool {
+    // Print any problems with the almanac, returning whether it's safe to solve.
+
+    if let Some(almanac) = fs::read_to_string("data/5.input").ok() {
+        if let Some((_, almanac)) = almanac.split_once("\n\n") {
+            let validation = validate_almanac(&get_almanac(almanac), start, end);
+
+            validation.warnings.iter().for_each(|warning| eprintln!("warning: {warning}"));
+            validation.errors.iter().for_each(|error| eprintln!("error: {error}"));
+
+            return validation.errors.is_empty();
+        }
+    }
+
+    panic!("file not found")
+}
+
 fn main() {
-    println!("part one: {}", get_smallest_seed());
-    println!("part two: {}", get_smallest_seed_from_range());
+    // `cargo run --bin day5 -- [composed] [<from> <to>]` converts between any two categories,
+    // defaulting to seed and location. `composed` solves through a single composed function.
+    // The numbers on the `seeds:` line are read as `<from>` values as they stand, they aren't
+    // mapped from seed to `<from>` first.
+
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+    let composed = args.first().is_some_and(|arg| arg == "composed");
+
+    let (start, end) = match args.iter().skip(composed as usize).map(|arg| arg.as_str()).collect::<Vec<&str>>()[..] {
+        [start, end] => (start, end),
+        _ => ("seed", "location")
+    };
+
+    if !validate(start, end) {
+        std::process::exit(1);
+    }
+
+    if start != "seed" {
+        println!("reading the seeds line as {start} values");
+    }
+
+    if composed {
+        let (singles, ranges) = get_smallest_seeds_composed(start, end);
+
+        for (part, lowest) in [("part one", singles), ("part two", ranges)] {
+            match lowest {
+                Some((location, seed)) => println!("{part}: {location} ({start} {seed})"),
+                None => println!("{part}: no seeds")
+            }
+        }
+    } else {
+        println!("part one: {}", get_smallest_seed(start, end));
+        println!("part two: {}", get_smallest_seed_from_range(start, end));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> (String, Vec<Map>) {
+        let input = fs::read_to_string("data/5.test").expect("sample almanac");
+        let (seeds, almanac) = input.split_once("\n\n").expect("seeds line");
+
+        return (seeds.to_string(), get_almanac(almanac));
+    }
+
+    #[test]
+    fn eval_agrees_with_find_values() {
+        let (seeds, almanac) = sample();
+        let chain = map_chain(&almanac, "seed", "location");
+        let function = compose_chain(&chain);
+
+        let seeds = get_seeds(&seeds);
+        let expected = find_values(seeds.clone(), &chain);
+
+        assert_eq!(seeds.iter().map(|seed| function.eval(*seed)).collect::<Vec<isize>>(), expected);
+    }
+
+    #[test]
+    fn eval_agrees_with_inverse() {
+        let (seeds, almanac) = sample();
+        let function = compose_chain(&map_chain(&almanac, "seed", "location"));
+
+        let singles = get_seeds(&seeds).into_iter().map(|seed| (seed, seed)).collect::<Vec<(isize, isize)>>();
+
+        for (ranges, lowest) in [(singles, 35), (get_seed_ranges(&seeds), 46)] {
+            let (location, seed) = lowest_location(&function, &ranges).expect("sample has seeds");
+
+            assert_eq!(location, lowest);
+            assert_eq!(function.eval(seed), location);
+        }
+    }
+
+    #[test]
+    fn no_seeds() {
+        let (_, almanac) = sample();
+        let function = compose_chain(&map_chain(&almanac, "seed", "location"));
+
+        assert_eq!(lowest_location(&function, &[]), None);
+    }
 }
@@ -1,35 +1,195 @@
 use std::fs;
 
-fn ways_to_win(input: String) -> usize {
+fn beats(hold: u128, time: u128, distance: u128) -> bool {
+    // Holding for `hold` travels `hold * (time - hold)`, which must strictly beat the record.
+    // A distance too big for u128 beats any record.
+
+    return match hold.checked_mul(time - hold) {
+        Some(travelled) => travelled > distance,
+        None => true
+    };
+}
+
+fn winning_interval(time: u128, distance: u128) -> Option<(u128, u128)> {
+    // Solve `hold * (time - hold) > distance` exactly, giving the first and last winning holds.
+    //
+    // The boundaries are the roots of `hold^2 - time * hold + distance = 0`, at
+    // `(time ± sqrt(time^2 - 4 * distance)) / 2`. The integer square root puts us within a step
+    // of the lower boundary, which we then nudge onto the first winning hold. The winning holds
+    // are symmetric around `time / 2`, so the upper boundary comes for free.
+    //
+    // A boundary landing exactly on the record only ties it, so it doesn't count.
+    //
+    // Races too long to square in u128 binary search the lower boundary instead, since the
+    // distance only grows up to `time / 2`.
+
+    if !beats(time / 2, time, distance) {
+        // The best possible hold can't beat the record.
+        return None;
+    }
+
+    let mut lowest = match time.checked_mul(time).zip(distance.checked_mul(4)) {
+        Some((square, quadruple)) => (time - (square - quadruple).isqrt()) / 2,
+        None => {
+            let (mut lo, mut hi) = (0, time / 2);
+
+            while lo < hi {
+                let mid = lo + (hi - lo) / 2;
+
+                if beats(mid, time, distance) {
+                    hi = mid;
+                } else {
+                    lo = mid + 1;
+                }
+            }
+
+            lo
+        }
+    };
+
+    while lowest <= time / 2 && !beats(lowest, time, distance) {
+        lowest += 1;
+    }
+
+    while lowest > 0 && beats(lowest - 1, time, distance) {
+        lowest -= 1;
+    }
+
+    if lowest > time / 2 {
+        return None;
+    }
+
+    return Some((lowest, time - lowest));
+}
+
+fn winning_holds(time: u128, distance: u128) -> u128 {
+    return match winning_interval(time, distance) {
+        Some((lowest, highest)) => highest - lowest + 1,
+        None => 0
+    };
+}
+
+fn races(input: &str) -> Result<Vec<(u128, u128)>, String> {
+    // Pair up each race's time and record distance, skipping the line labels.
+
+    let numbers = |line: &str| {
+        line.split_whitespace()
+            .skip(1)
+            .map(|v| v.parse::<u128>().map_err(|_| format!("improper number: {v}")))
+            .collect::<Result<Vec<u128>, String>>()
+    };
+
     if let Some((time, distance)) = input.split_once("\n") {
-        let time = time
-            .split_whitespace()
-            .filter_map(|v| v.parse::<usize>().ok())
-            .collect::<Vec<usize>>();
-
-        let distance = distance
-            .split_whitespace()
-            .filter_map(|v| v.parse::<usize>().ok())
-            .collect::<Vec<usize>>();
-
-        // Count the product of the possible ways to win.
-        return time
-            .iter()
-            .zip(distance)
-            .map(|(t, d)| {
-                (0..=*t).enumerate().map(|(strategy, idx)| {
-                    (t - strategy) * idx > d
-                }).filter(|n| *n).count()
-            }).product::<usize>();
+        let (time, distance) = (numbers(time)?, numbers(distance)?);
+
+        if time.len() != distance.len() {
+            return Err(format!("{} times but {} distances", time.len(), distance.len()));
+        }
+
+        return Ok(time.into_iter().zip(distance).collect());
     }
 
     panic!("incorrect file format")
 }
 
+fn ways_to_win(input: String) -> Result<u128, String> {
+    // Count the product of the possible ways to win.
+
+    return races(&input)?
+        .iter()
+        .map(|(t, d)| winning_holds(*t, *d))
+        .try_fold(1u128, |product, ways| {
+            product.checked_mul(ways).ok_or_else(|| "product of ways to win overflows u128".to_string())
+        });
+}
+
+fn explain_races(input: &str) -> Result<(), String> {
+    // Print each race's winning interval, its optimal hold time, and the margin that gives over
+    // the record. Distance peaks at `time / 2`, odd times tie with `time / 2 + 1`.
+
+    races(input)?.iter().enumerate().for_each(|(idx, (time, record))| {
+        let hold = time / 2;
+        let (best, margin) = match hold.checked_mul(time - hold) {
+            Some(best) if best >= *record => (best.to_string(), format!("+{}", best - record)),
+            Some(best) => (best.to_string(), format!("-{}", record - best)),
+            None => ("more than u128 can hold".to_string(), "+?".to_string())
+        };
+
+        let interval = match winning_interval(*time, *record) {
+            Some((lowest, highest)) => format!("holds {lowest}..={highest} win ({} ways)", highest - lowest + 1),
+            None => "no winning holds".to_string()
+        };
+
+        println!(
+            "race {}: {time}ms, record {record}mm: {interval}, best hold {hold}ms goes {best}mm ({margin}mm)",
+            idx + 1
+        );
+    });
+
+    return Ok(());
+}
+
+fn distance_curve(input: &str, step: u128) -> Result<String, String> {
+    // Distance travelled for every `step`th hold time of every race, as CSV.
+
+    let mut csv = String::from("race,hold,distance,record,wins\n");
+
+    races(input)?.iter().enumerate().for_each(|(idx, (time, record))| {
+        let mut hold = 0;
+
+        while hold <= *time {
+            let distance = hold.checked_mul(time - hold).map_or("overflow".to_string(), |d| d.to_string());
+
+            csv.push_str(&format!("{},{hold},{distance},{record},{}\n", idx + 1, beats(hold, *time, *record)));
+
+            hold = match hold.checked_add(step) {
+                Some(hold) => hold,
+                None => break
+            };
+        }
+    });
+
+    return Ok(csv);
+}
+
 fn main() {
+    // `cargo run --bin day6 -- explore` breaks down each race,
+    // `cargo run --bin day6 -- curve [kerned] [step]` prints the distance-vs-hold curve as CSV.
+
     if let Some(input) = fs::read_to_string("data/6.input").ok() {
-        println!("part one: {}", ways_to_win(input.clone()));
-        println!("part two: {}", ways_to_win(input.replace(" ", "").replace(":", " ")));
+        let kerned = input.replace(" ", "").replace(":", " ");
+        let args = std::env::args().skip(1).collect::<Vec<String>>();
+        let fail = |err: String| -> ! {
+            eprintln!("error: {err}");
+            std::process::exit(1)
+        };
+
+        match args.iter().map(|arg| arg.as_str()).collect::<Vec<&str>>()[..] {
+            ["explore"] => {
+                println!("spaced:");
+                explain_races(&input).unwrap_or_else(|err| fail(err));
+                println!("kerned:");
+                explain_races(&kerned).unwrap_or_else(|err| fail(err));
+            },
+            ["curve", ref rest @ ..] => {
+                let reading = if rest.first() == Some(&"kerned") { &kerned } else { &input };
+                let step = rest
+                    .last()
+                    .and_then(|step| step.parse::<u128>().ok())
+                    .unwrap_or(1)
+                    .max(1);
+
+                print!("{}", distance_curve(reading, step).unwrap_or_else(|err| fail(err)));
+            },
+            _ => {
+                for (part, reading) in [("part one", input.clone()), ("part two", kerned)] {
+                    match ways_to_win(reading) {
+                        Ok(ways) => println!("{part}: {ways}"),
+                        Err(err) => println!("{part}: {err}")
+                    }
+                }
+            }
+        }
     } else {
         panic!("file not found")
     }
@@ -2,20 +2,134 @@ use std::fs;
 use std::cmp::Ordering;
 use std::collections::HashMap;
 
-enum Ruleset {
-    Standard,
-    Jokers
+#[derive(Clone)]
+struct Ruleset {
+    name: String,
+    // Card faces, from lowest to highest.
+    order: Vec<char>,
+    wild: Option<char>,
+    hand_size: usize,
+    straights: bool,
+    flushes: bool,
+    // Groups of faces which count as the same suit, for flushes.
+    suits: Vec<Vec<char>>
 }
 
-#[derive(PartialEq, PartialOrd, Eq)]
+impl Ruleset {
+    fn standard() -> Ruleset {
+        return Ruleset {
+            name: "standard".to_string(),
+            order: "23456789TJQKA".chars().collect(),
+            wild: None,
+            hand_size: 5,
+            straights: false,
+            flushes: false,
+            suits: vec![]
+        };
+    }
+
+    fn jokers() -> Ruleset {
+        // Jokers are wild, but the weakest card when breaking ties.
+
+        return Ruleset {
+            name: "jokers".to_string(),
+            order: "J23456789TQKA".chars().collect(),
+            wild: Some('J'),
+            ..Ruleset::standard()
+        };
+    }
+
+    fn parse(definition: &str) -> Result<Ruleset, String> {
+        // A ruleset is a block of `key: value` lines, e.g.
+        //
+        //     name: jokers
+        //     order: J23456789TQKA
+        //     wild: J
+        //     hand size: 5
+        //     straights: yes
+        //     flushes: yes
+        //     suits: 2468TQ 3579JKA
+        //
+        // Only `order` is required: by default nothing is wild, hands have five cards, and
+        // straights and flushes don't count.
+
+        let mut ruleset = Ruleset { name: String::new(), order: vec![], ..Ruleset::standard() };
+
+        for line in definition.lines().filter(|line| !line.trim().is_empty()) {
+            let (key, value) = line
+                .split_once(':')
+                .map(|(key, value)| (key.trim(), value.trim()))
+                .ok_or(format!("improper rule: {line}"))?;
+
+            let flag = || match value {
+                "yes" | "true" => Ok(true),
+                "no" | "false" => Ok(false),
+                _ => Err(format!("improper {key}: {value}"))
+            };
+
+            match key {
+                "name" => ruleset.name = value.to_string(),
+                "order" => ruleset.order = value.chars().collect(),
+                "wild" => ruleset.wild = value.chars().next(),
+                "hand size" => ruleset.hand_size = value
+                    .parse::<usize>()
+                    .map_err(|_| format!("improper hand size: {value}"))?,
+                "straights" => ruleset.straights = flag()?,
+                "flushes" => ruleset.flushes = flag()?,
+                "suits" => ruleset.suits = value
+                    .split_whitespace()
+                    .map(|suit| suit.chars().collect())
+                    .collect(),
+                _ => return Err(format!("unknown rule: {key}"))
+            }
+        }
+
+        if ruleset.order.is_empty() {
+            return Err(format!("ruleset {} has no card order", ruleset.name));
+        }
+
+        if let Some(wild) = ruleset.wild {
+            if !ruleset.order.contains(&wild) {
+                return Err(format!("wild card {wild} isn't in the card order"));
+            }
+        }
+
+        return Ok(ruleset);
+    }
+
+    fn value(&self, face: char) -> Option<usize> {
+        // Card values start from 1, for the lowest card.
+
+        return self.order.iter().position(|c| *c == face).map(|idx| idx + 1);
+    }
+
+    fn is_wild(&self, value: usize) -> bool {
+        return self.wild.is_some_and(|wild| self.value(wild) == Some(value));
+    }
+
+    fn suit(&self, value: usize) -> Option<usize> {
+        let face = self.order[value - 1];
+
+        return self.suits.iter().position(|suit| suit.contains(&face));
+    }
+
+    fn faces(&self, cards: &[usize]) -> String {
+        return cards.iter().map(|card| self.order[card - 1]).collect();
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd, Eq, Ord)]
 enum Rank {
-    Five    = 7,
-    Four    = 6,
-    Full    = 5,
-    Three   = 4,
-    TwoPair = 3,
-    OnePair = 2,
-    High   = 1
+    Five          = 10,
+    StraightFlush = 9,
+    Four          = 8,
+    Full          = 7,
+    Flush         = 6,
+    Straight      = 5,
+    Three         = 4,
+    TwoPair       = 3,
+    OnePair       = 2,
+    High          = 1
 }
 
 #[derive(PartialEq, Eq)]
@@ -54,111 +168,288 @@ impl Ord for Hand {
     }
 }
 
-fn determine_rank(cards: &Vec<usize>, ruleset: &Ruleset) -> Rank {
+fn determine_rank(cards: &[usize], ruleset: &Ruleset) -> Rank {
+    if (ruleset.straights || ruleset.flushes) && cards.iter().any(|card| ruleset.is_wild(*card)) {
+        // Suits are groups of faces, so a wild card can't be any face and any suit at once.
+        // Picking its face greedily can't account for that, so try every substitution.
+        return best_substitution(cards, ruleset).1;
+    }
+
     let mut counts: HashMap<usize, usize> = HashMap::new();
 
-    match ruleset {
-        Ruleset::Standard => {
-            cards.iter().for_each(|card| {
-                counts.entry(*card).and_modify(|v| *v += 1).or_insert(1);
-            });
-        },
-        Ruleset::Jokers => {
-            // Find the best card number to add your jokers to.
-
-            cards.iter().for_each(|card| {
-                if card != &1 {
-                    counts.entry(*card).and_modify(|v| *v += 1).or_insert(1);
-                }
-            });
+    cards.iter().for_each(|card| {
+        if !ruleset.is_wild(*card) {
+            counts.entry(*card).and_modify(|v| *v += 1).or_insert(1);
+        }
+    });
 
-            let mut best_to_add_to = counts.clone().into_iter().collect::<Vec<(usize, usize)>>();
-            let number_of_jokers = cards.iter().filter(|card| *card == &1).count();
+    let number_of_wilds = cards.iter().filter(|card| ruleset.is_wild(**card)).count();
 
-            best_to_add_to.sort_by(|a, b| {
-                // Sort by number of appearances, then by highest card.
-                return match b.1.cmp(&a.1) {
-                    Ordering::Less => Ordering::Less,
-                    Ordering::Greater => Ordering::Greater,
-                    Ordering::Equal => b.0.cmp(&a.0)
-                }
-            });
-
-            if best_to_add_to.len() == 0 {
-                // Only thing is jokers, you have five-of-a-kind jokers.
-                counts
-                    .entry(1)
-                    .or_insert(5);
-            } else {
-                counts
-                    .entry(best_to_add_to[0].0)
-                    .and_modify(|v| *v += number_of_jokers);
+    if number_of_wilds > 0 {
+        // Find the best card number to add your wild cards to.
+
+        let mut best_to_add_to = counts.clone().into_iter().collect::<Vec<(usize, usize)>>();
+
+        best_to_add_to.sort_by(|a, b| {
+            // Sort by number of appearances, then by highest card.
+            return match b.1.cmp(&a.1) {
+                Ordering::Less => Ordering::Less,
+                Ordering::Greater => Ordering::Greater,
+                Ordering::Equal => b.0.cmp(&a.0)
             }
+        });
 
+        if best_to_add_to.len() == 0 {
+            // Only thing is wild cards, you have a whole hand of a kind.
+            counts.insert(cards[0], number_of_wilds);
+        } else {
+            counts
+                .entry(best_to_add_to[0].0)
+                .and_modify(|v| *v += number_of_wilds);
         }
     }
 
     let mut cols = counts.into_values().collect::<Vec<usize>>();
     cols.sort_by(|a, b| b.cmp(a));
 
-    return match cols.iter().nth(0) {
-        Some(5) => Rank::Five,
+    let rank = match cols.iter().nth(0) {
+        Some(5..) => Rank::Five,
         Some(4) => Rank::Four,
         Some(3) if cols.iter().nth(1) == Some(&2) => Rank::Full,
         Some(3) => Rank::Three,
         Some(2) if cols.iter().nth(1) == Some(&2) => Rank::TwoPair,
         Some(2) => Rank::OnePair,
         _ => Rank::High
-    }
+    };
+
+    // Wild cards were substituted above whenever these count, so every card here is natural.
+    // A straight needs distinct, consecutive cards, and a flush needs them all in one suit.
+    let mut sorted = cards.to_vec();
+    sorted.sort();
+
+    let straight = ruleset.straights &&
+        sorted.windows(2).all(|pair| pair[0] + 1 == pair[1]);
+
+    let flush = ruleset.flushes &&
+        sorted.first().is_some_and(|first| {
+            let suit = ruleset.suit(*first);
+            suit.is_some() && sorted.iter().all(|card| ruleset.suit(*card) == suit)
+        });
+
+    let special = match (straight, flush) {
+        (true, true) => Rank::StraightFlush,
+        (false, true) => Rank::Flush,
+        (true, false) => Rank::Straight,
+        (false, false) => Rank::High
+    };
+
+    return if special > rank { special } else { rank };
 }
 
-fn parse_line(line: &str, ruleset: &Ruleset) -> Option<Hand> {
-    if let Some((cards, bid)) = line.split_once(" ") {
-        let cards = cards
+fn parse_line(line: &str, ruleset: &Ruleset) -> Result<Hand, String> {
+    if let Some((faces, bid)) = line.split_once(" ") {
+        let cards = faces
             .chars()
-            .filter_map(|c| {
-                return match c.to_digit(10) {
-                    Some(v) => Some(v as usize),
-                    None => match c {
-                        'T' => Some(10),
-                        'J' => match ruleset {
-                            Ruleset::Standard => Some(11),
-                            Ruleset::Jokers => Some(1),
-                        },
-                        'Q' => Some(12),
-                        'K' => Some(13),
-                        'A' => Some(14),
-                        _ => None
-                    }
-                }
-            }).collect::<Vec<usize>>();
+            .map(|c| ruleset.value(c).ok_or(format!("{c} isn't a card in {}", ruleset.name)))
+            .collect::<Result<Vec<usize>, String>>()?;
 
-        if let Some(bid) = bid.parse::<usize>().ok() {
-            let rank = determine_rank(&cards, ruleset);
-            return Some(Hand { rank, cards, bid })
+        if cards.len() != ruleset.hand_size {
+            return Err(format!(
+                "{faces} has {} cards, but {} hands have {}",
+                cards.len(), ruleset.name, ruleset.hand_size
+            ));
         }
+
+        let bid = bid.trim().parse::<usize>().map_err(|_| format!("improper bid: {bid}"))?;
+        let rank = determine_rank(&cards, ruleset);
+
+        return Ok(Hand { rank, cards, bid });
     }
 
-    None
+    Err(format!("improper hand: {line}"))
 }
 
-fn sum_of_winnings(ruleset: Ruleset) -> usize {
-    // Build cards based on a ruleset, since jokers mode only changes a few things.
+fn get_hands(ruleset: &Ruleset) -> Result<Vec<Hand>, String> {
+    // Every hand in the input, reporting the first that doesn't fit the ruleset.
+
     if let Some(input) = fs::read_to_string("data/7.input").ok() {
-        let mut winnings = input
+        return input
             .lines()
-            .filter_map(|line| parse_line(line, &ruleset))
-            .collect::<Vec<Hand>>();
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(idx, line)| parse_line(line, ruleset).map_err(|e| format!("line {}: {e}", idx + 1)))
+            .collect();
+    }
 
-        winnings.sort();
+    panic!("file not found")
+}
 
-        return winnings.iter().enumerate().map(|(idx, w)| (idx + 1) * w.bid).sum();
+fn load_rulesets(path: &str) -> Vec<Ruleset> {
+    // Rulesets are separated by blank lines.
+
+    if let Some(rules) = fs::read_to_string(path).ok() {
+        return rules
+            .split("\n\n")
+            .filter(|definition| !definition.trim().is_empty())
+            .map(|definition| match Ruleset::parse(definition) {
+                Ok(ruleset) => ruleset,
+                Err(e) => panic!("{path}: {e}")
+            }).collect();
     }
 
-    panic!("file not found")
+    panic!("file not found: {path}")
+}
+
+fn best_substitution(cards: &[usize], ruleset: &Ruleset) -> (Vec<usize>, Rank) {
+    // Try every way of replacing the wild cards with natural faces, keeping the best rank.
+    // Order doesn't matter to a rank, so only multisets of replacements are tried: at most
+    // C(16, 5) = 4368 for a five-card hand of twelve natural faces.
+
+    let natural = Ruleset { wild: None, ..ruleset.clone() };
+    let faces = (1..=ruleset.order.len())
+        .filter(|value| !ruleset.is_wild(*value))
+        .collect::<Vec<usize>>();
+    let wilds = cards
+        .iter()
+        .enumerate()
+        .filter(|(_, card)| ruleset.is_wild(**card))
+        .map(|(idx, _)| idx)
+        .collect::<Vec<usize>>();
+
+    let mut best = (cards.to_vec(), determine_rank(cards, &natural));
+
+    if wilds.is_empty() || faces.is_empty() {
+        return best;
+    }
+
+    // Indices into `faces` for each wild, kept non-decreasing.
+    let mut picks = vec![0; wilds.len()];
+
+    loop {
+        let mut resolved = cards.to_vec();
+
+        wilds.iter().zip(picks.iter()).for_each(|(idx, pick)| resolved[*idx] = faces[*pick]);
+
+        let rank = determine_rank(&resolved, &natural);
+
+        if (rank, &resolved) > (best.1, &best.0) {
+            best = (resolved, rank);
+        }
+
+        // Advance to the next multiset.
+        if let Some(idx) = picks.iter().rposition(|pick| *pick + 1 < faces.len()) {
+            let next = picks[idx] + 1;
+            picks[idx..].iter_mut().for_each(|pick| *pick = next);
+        } else {
+            return best;
+        }
+    }
+}
+
+fn explain_winnings(ruleset: &Ruleset) -> Result<usize, String> {
+    // Score the hands with every wild card substitution tried, printing how each hand placed.
+    // Any hand where the greedy choice in `determine_rank` differs from the exhaustive one is
+    // flagged.
+
+    let mut winnings = get_hands(ruleset)?
+        .into_iter()
+        .map(|hand| {
+            let (resolved, rank) = best_substitution(&hand.cards, ruleset);
+            let greedy = hand.rank;
+
+            return (Hand { rank, ..hand }, resolved, greedy);
+        })
+        .collect::<Vec<(Hand, Vec<usize>, Rank)>>();
+
+    winnings.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let compare = |hand: &Hand, other: &Hand| -> String {
+        if hand.rank != other.rank {
+            return format!("{:?} vs {:?}", hand.rank, other.rank);
+        }
+
+        return match hand.cards.iter().zip(&other.cards).position(|(a, b)| a != b) {
+            Some(idx) => format!(
+                "card {}: {} vs {}",
+                idx + 1,
+                ruleset.faces(&hand.cards[idx..=idx]),
+                ruleset.faces(&other.cards[idx..=idx])
+            ),
+            None => "identical".to_string()
+        };
+    };
+
+    println!("{}:", ruleset.name);
+
+    for (idx, (hand, resolved, greedy)) in winnings.iter().enumerate() {
+        let mut line = format!(
+            "#{} {} -> {} ({:?})",
+            idx + 1,
+            ruleset.faces(&hand.cards),
+            ruleset.faces(resolved),
+            hand.rank
+        );
+
+        if let Some((below, _, _)) = idx.checked_sub(1).map(|below| &winnings[below]) {
+            line.push_str(&format!(", beats {} ({})", ruleset.faces(&below.cards), compare(hand, below)));
+        }
+
+        if let Some((above, _, _)) = winnings.get(idx + 1) {
+            line.push_str(&format!(", loses to {} ({})", ruleset.faces(&above.cards), compare(hand, above)));
+        }
+
+        line.push_str(&format!(", bid {} * {} = {}", hand.bid, idx + 1, hand.bid * (idx + 1)));
+
+        if *greedy != hand.rank {
+            line.push_str(&format!(" [greedy choice was {greedy:?}]"));
+        }
+
+        println!("{line}");
+    }
+
+    return Ok(winnings.iter().enumerate().map(|(idx, w)| (idx + 1) * w.0.bid).sum());
+}
+
+fn sum_of_winnings(ruleset: &Ruleset) -> Result<usize, String> {
+    // Build cards based on a ruleset, since jokers mode only changes a few things.
+
+    let mut winnings = get_hands(ruleset)?;
+
+    winnings.sort();
+
+    return Ok(winnings.iter().enumerate().map(|(idx, w)| (idx + 1) * w.bid).sum());
 }
 
 fn main() {
-    println!("part one: {}", sum_of_winnings(Ruleset::Standard));
-    println!("part two: {}", sum_of_winnings(Ruleset::Jokers));
+    // `cargo run --bin day7 -- [explain] [<rules file>]` scores the hands under every ruleset in
+    // the file, see `data/7.rules`. `explain` tries every wild card substitution and prints how
+    // each hand placed.
+
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+    let report = |label: &str, total: Result<usize, String>| match total {
+        Ok(total) => println!("{label}: {total}"),
+        Err(e) => {
+            eprintln!("{label}: {e}");
+            std::process::exit(1);
+        }
+    };
+
+    let (explain, rulesets) = match args.iter().map(|arg| arg.as_str()).collect::<Vec<&str>>()[..] {
+        ["explain"] => (true, vec![Ruleset::standard(), Ruleset::jokers()]),
+        ["explain", path] => (true, load_rulesets(path)),
+        [path] => (false, load_rulesets(path)),
+        _ => {
+            report("part one", sum_of_winnings(&Ruleset::standard()));
+            report("part two", sum_of_winnings(&Ruleset::jokers()));
+            return;
+        }
+    };
+
+    rulesets.iter().for_each(|ruleset| {
+        if explain {
+            report("total", explain_winnings(ruleset));
+        } else {
+            report(&ruleset.name, sum_of_winnings(ruleset));
+        }
+    });
 }
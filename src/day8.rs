@@ -1,71 +1,68 @@
 use std::fs;
 use std::collections::HashMap;
-use std::iter::Cycle;
-use std::str::Chars;
 
-fn gcd(mut a: usize, mut b: usize) -> usize {
-    // gcd snippet.
-
-    if a == b { return a; }
-    (a, b) = (a.min(b), a.max(b));
-    while b > 0 {
-        (a, b) = (b, a % b);
-    }
-
-    return a;
+struct Network {
+    names: Vec<String>,
+    ids: HashMap<String, usize>,
+    // The left and right neighbours of each node, by id.
+    edges: Vec<[usize; 2]>,
+    // Each instruction, as an index into `edges`: 0 for left, 1 for right.
+    instructions: Vec<usize>
 }
 
-fn lcm(a: usize, b: usize) -> usize {
-    // lcm snippet
-
-    return a * (b / gcd(a, b));
-}
+impl Network {
+    fn targets(&self, suffix: &str) -> Vec<bool> {
+        // Which nodes end in the given suffix, by id.
 
-fn find_steps(
-    network: &HashMap<String, (String, String)>,
-    start: String,
-    end: &str,
-    instructions: &mut Cycle<Chars<'_>>,
-    count: usize
-) -> usize {
-    if start.ends_with(&end) {
-        return count;
+        return self.names.iter().map(|name| name.ends_with(suffix)).collect();
     }
-
-    if let Some(node) = network.get(&start) {
-        let next = match instructions.next() {
-            Some('L') => &node.0,
-            Some('R') => &node.1,
-            _ => unreachable!()
-        };
-
-        return find_steps(network, next.to_string(), end, instructions, count + 1);
-    }
-
-    panic!("can't find node {start}")
 }
 
-fn path_steps() -> usize {
+fn get_network() -> Network {
+    // Parse the network, interning node names as dense ids.
+
     if let Some(input) = fs::read_to_string("data/8.input").ok() {
         if let Some((instructions, network)) = input.split_once("\n\n") {
-            let mut instructions = instructions.chars().cycle();
-            let network = network.lines().filter_map(|node| {
+            let instructions = instructions
+                .trim()
+                .chars()
+                .map(|c| match c {
+                    'L' => 0,
+                    'R' => 1,
+                    _ => panic!("improper instruction: {c}")
+                }).collect::<Vec<usize>>();
+
+            let nodes = network.lines().filter_map(|node| {
                 let node = node.replace("(", "").replace(")", "");
 
                 if let Some((name, coords)) = node.split_once(" = ") {
                     if let Some((left, right)) = coords.split_once(", ") {
-                        return Some((
-                            name.to_string(),
-                            (left.to_string(), right.to_string())
-                        ));
+                        return Some((name.to_string(), left.to_string(), right.to_string()));
                     }
                 }
 
                 None
-            }).collect::<HashMap<String, (String, String)>>();
+            }).collect::<Vec<(String, String, String)>>();
+
+            let ids = nodes
+                .iter()
+                .enumerate()
+                .map(|(id, (name, _, _))| (name.clone(), id))
+                .collect::<HashMap<String, usize>>();
+
+            let id = |name: &String| match ids.get(name) {
+                Some(id) => *id,
+                None => panic!("can't find node {name}")
+            };
+
+            let edges = nodes
+                .iter()
+                .map(|(_, left, right)| [id(left), id(right)])
+                .collect::<Vec<[usize; 2]>>();
 
-            return find_steps(&network, "AAA".to_string(), "ZZZ", &mut instructions, 0);
+            let names = nodes.into_iter().map(|(name, _, _)| name).collect();
 
+            return Network { names, ids, edges, instructions };
         }
 
         panic!("improper file format");
@@ -74,43 +71,312 @@ fn path_steps() -> usize {
     panic!("file not found")
 }
 
-fn ghost_steps() -> usize {
-    if let Some(input) = fs::read_to_string("data/8.input").ok() {
-        if let Some((instructions, network)) = input.split_once("\n\n") {
-            let mut instruction_cycle = instructions.chars().cycle();
-            let network = network.lines().filter_map(|node| {
-                let node = node.replace("(", "").replace(")", "");
+fn extended_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    // Returns `(g, x, y)` where `a * x + b * y = g = gcd(a, b)`.
 
-                if let Some((name, coords)) = node.split_once(" = ") {
-                    if let Some((left, right)) = coords.split_once(", ") {
-                        return Some((
-                            name.to_string(),
-                            (left.to_string(), right.to_string())
-                        ));
-                    }
-                }
+    if b == 0 {
+        return (a, 1, 0);
+    }
 
-                None
-            }).collect::<HashMap<String, (String, String)>>();
-
-            // Find the lcm of each path, against the length of the instruction loop.
-            return network
-                .clone()
-                .into_keys()
-                .filter(|k| k.ends_with("A"))
-                .map(|start| {
-                    find_steps(&network, start.to_string(), "Z", &mut instruction_cycle, 0)
-                })
-                .fold(instructions.len(), |acc, x| lcm(acc, x));
+    let (g, x, y) = extended_gcd(b, a.rem_euclid(b));
+
+    return (g, y, x - a.div_euclid(b) * y);
+}
+
+fn crt(a: (i128, i128), b: (i128, i128)) -> Option<(i128, i128)> {
+    // Generalized Chinese Remainder Theorem: combine `x ≡ a.0 (mod a.1)` and `x ≡ b.0 (mod b.1)`
+    // into a single `x ≡ r (mod lcm)`, if the two agree wherever their moduli overlap.
+
+    let (g, p, _) = extended_gcd(a.1, b.1);
+
+    if (b.0 - a.0) % g != 0 {
+        return None;
+    }
+
+    let lcm = a.1 / g * b.1;
+    let step = ((b.0 - a.0) / g).rem_euclid(b.1 / g) * p.rem_euclid(b.1 / g) % (b.1 / g);
+
+    return Some(((a.0 + a.1 * step).rem_euclid(lcm), lcm));
+}
+
+struct Ghost {
+    // Steps before the walk enters its cycle, and the length of that cycle.
+    pre_period: usize,
+    period: usize,
+    // Every step within the first `pre_period + period` steps where the ghost stands on a Z node.
+    hits: Vec<usize>
+}
+
+impl Ghost {
+    fn on_z(&self, step: usize) -> bool {
+        // Steps past the first lap of the cycle are folded back onto it.
+
+        let step = if step < self.pre_period + self.period {
+            step
+        } else {
+            (step - self.pre_period) % self.period + self.pre_period
+        };
+
+        return self.hits.binary_search(&step).is_ok();
+    }
+}
+
+fn find_cycle(network: &Network, start: usize, targets: &[bool]) -> Ghost {
+    // A ghost's state is its node along with its position in the instructions, so the walk
+    // must repeat once a state is revisited. States are numbered densely, so `seen` is a table.
+
+    let length = network.instructions.len();
+    let mut seen: Vec<Option<usize>> = vec![None; network.edges.len() * length];
+    let mut hits = vec![];
+    let mut node = start;
+    let mut step = 0;
+
+    loop {
+        let position = step % length;
+        let state = node * length + position;
+
+        if let Some(first) = seen[state] {
+            return Ghost { pre_period: first, period: step - first, hits };
         }
 
-        panic!("improper file format");
+        seen[state] = Some(step);
+
+        if targets[node] {
+            hits.push(step);
+        }
+
+        node = network.edges[node][network.instructions[position]];
+        step += 1;
     }
+}
 
-    panic!("file not found")
+fn sync_ghosts(ghosts: &[Ghost]) -> Option<usize> {
+    // Find the first step where every ghost is on a Z node.
+    //
+    // Before every ghost has entered its cycle, simply check each hit of the slowest ghost to
+    // settle, whose hits cover all of those steps.
+    // After that, each ghost is on a Z node at `hit + k * period` for any of its in-cycle hits,
+    // so every combination of hits is solved with the CRT, and the earliest solution wins.
+
+    let slowest = ghosts.iter().max_by_key(|ghost| ghost.pre_period)?;
+    let settled = slowest.pre_period;
+
+    if let Some(step) = slowest.hits
+        .iter()
+        .filter(|step| **step < settled)
+        .find(|step| ghosts.iter().all(|ghost| ghost.on_z(**step)))
+    {
+        return Some(*step);
+    }
+
+    let mut solutions = vec![(0, 1)];
+
+    for ghost in ghosts.iter() {
+        let period = ghost.period as i128;
+
+        solutions = solutions
+            .iter()
+            .flat_map(|solution| {
+                ghost.hits
+                    .iter()
+                    .filter(|hit| **hit >= ghost.pre_period)
+                    .filter_map(|hit| crt(*solution, (*hit as i128 % period, period)))
+                    .collect::<Vec<(i128, i128)>>()
+            }).collect();
+
+        solutions.sort();
+        solutions.dedup();
+    }
+
+    return solutions
+        .iter()
+        .map(|(residue, modulus)| {
+            // The smallest step past `settled` with the right residue.
+            let settled = settled as i128;
+
+            if *residue >= settled {
+                *residue
+            } else {
+                residue + (settled - residue + modulus - 1) / modulus * modulus
+            }
+        })
+        .min()
+        .map(|step| usize::try_from(step).expect("ghosts take too many steps to sync"));
+}
+
+fn find_steps(network: &Network, start: usize, targets: &[bool]) -> Option<usize> {
+    // Walk from `start` until reaching a target.
+    // After visiting every (node, instruction) state the walk must be looping, so give up.
+
+    let limit = network.edges.len() * network.instructions.len();
+    let mut node = start;
+
+    for step in 0..=limit {
+        if targets[node] {
+            return Some(step);
+        }
+
+        node = network.edges[node][network.instructions[step % network.instructions.len()]];
+    }
+
+    None
+}
+
+fn path_steps() -> Option<usize> {
+    let network = get_network();
+
+    if let (Some(start), Some(end)) = (network.ids.get("AAA"), network.ids.get("ZZZ")) {
+        let mut targets = vec![false; network.edges.len()];
+        targets[*end] = true;
+
+        return find_steps(&network, *start, &targets);
+    }
+
+    panic!("network needs an AAA and a ZZZ node");
+}
+
+fn ghost_steps() -> Option<usize> {
+    // Analyse each ghost's cycle, then find where they all line up.
+
+    let network = get_network();
+    let targets = network.targets("Z");
+
+    let ghosts = network
+        .targets("A")
+        .iter()
+        .enumerate()
+        .filter(|(_, start)| **start)
+        .map(|(start, _)| find_cycle(&network, start, &targets))
+        .collect::<Vec<Ghost>>();
+
+    return sync_ghosts(&ghosts);
+}
+
+fn reachable(network: &Network, start: usize) -> Vec<bool> {
+    // Every node reachable from `start` along either edge, regardless of the instructions.
+
+    let mut seen = vec![false; network.edges.len()];
+    let mut queue = vec![start];
+    seen[start] = true;
+
+    while let Some(node) = queue.pop() {
+        for next in network.edges[node] {
+            if !seen[next] {
+                seen[next] = true;
+                queue.push(next);
+            }
+        }
+    }
+
+    return seen;
+}
+
+fn network_dot() -> String {
+    // Export the network as Graphviz DOT, with start nodes green and end nodes red.
+
+    let network = get_network();
+    let mut dot = String::from("digraph network {\n");
+
+    network.names.iter().for_each(|name| {
+        let style = if name.ends_with('A') {
+            " [style=filled, fillcolor=palegreen]"
+        } else if name.ends_with('Z') {
+            " [style=filled, fillcolor=lightcoral]"
+        } else {
+            ""
+        };
+
+        dot.push_str(&format!("    \"{name}\"{style};\n"));
+    });
+
+    network.edges.iter().enumerate().for_each(|(node, [left, right])| {
+        let name = &network.names[node];
+
+        dot.push_str(&format!("    \"{name}\" -> \"{}\" [label=L];\n", network.names[*left]));
+        dot.push_str(&format!("    \"{name}\" -> \"{}\" [label=R];\n", network.names[*right]));
+    });
+
+    dot.push_str("}\n");
+
+    return dot;
+}
+
+fn analyse_network() {
+    // Sanity checks to run before trusting an answer: nodes which can't reach a Z node, nodes
+    // which loop back to themselves, dead ends which can never be left, and how much of the
+    // network each ghost can wander into.
+
+    let network = get_network();
+    let targets = network.targets("Z");
+    let names = |nodes: Vec<usize>| -> String {
+        if nodes.is_empty() {
+            return "none".to_string();
+        }
+
+        return nodes.iter().map(|node| network.names[*node].as_str()).collect::<Vec<&str>>().join(", ");
+    };
+
+    // Walk the edges backwards from every Z node.
+    let mut incoming: Vec<Vec<usize>> = vec![vec![]; network.edges.len()];
+
+    network.edges.iter().enumerate().for_each(|(node, edges)| {
+        edges.iter().for_each(|next| incoming[*next].push(node));
+    });
+
+    let mut reaches_z = targets.clone();
+    let mut queue = (0..network.edges.len()).filter(|node| targets[*node]).collect::<Vec<usize>>();
+
+    while let Some(node) = queue.pop() {
+        for previous in incoming[node].iter() {
+            if !reaches_z[*previous] {
+                reaches_z[*previous] = true;
+                queue.push(*previous);
+            }
+        }
+    }
+
+    let stranded = (0..network.edges.len()).filter(|node| !reaches_z[*node]).collect();
+    let self_loops = (0..network.edges.len()).filter(|node| network.edges[*node].contains(node)).collect();
+    let dead_ends = (0..network.edges.len()).filter(|node| network.edges[*node] == [*node, *node]).collect();
+
+    println!("can't reach a Z node: {}", names(stranded));
+    println!("self-loops: {}", names(self_loops));
+    println!("dead ends: {}", names(dead_ends));
+
+    network.targets("A").iter().enumerate().filter(|(_, start)| **start).for_each(|(start, _)| {
+        let subgraph = reachable(&network, start);
+
+        println!(
+            "ghost {}: reaches {} nodes, {} of them Z nodes",
+            network.names[start],
+            subgraph.iter().filter(|seen| **seen).count(),
+            subgraph.iter().zip(targets.iter()).filter(|(seen, z)| **seen && **z).count()
+        );
+    });
 }
 
 fn main() {
-    println!("part one: {}", path_steps());
-    println!("part two: {}", ghost_steps());
+    // `cargo run --bin day8 -- dot` exports the network, `analyse` sanity checks it.
+
+    match std::env::args().nth(1).as_deref() {
+        Some("dot") => {
+            print!("{}", network_dot());
+            return;
+        },
+        Some("analyse") => {
+            analyse_network();
+            return;
+        },
+        _ => ()
+    }
+
+    match path_steps() {
+        Some(steps) => println!("part one: {steps}"),
+        None => println!("part one: ZZZ can't be reached from AAA")
+    }
+
+    match ghost_steps() {
+        Some(steps) => println!("part two: {steps}"),
+        None => println!("part two: the ghosts never line up")
+    }
 }
@@ -5,11 +5,16 @@ enum Direction {
     History
 }
 
-fn unfurl_reading(reading: Vec<isize>, direction: &Direction) -> isize {
+fn unfurl_reading(reading: Vec<isize>, direction: &Direction) -> (isize, bool) {
     // Extrapolate by creating new layers, which represent the difference between windows of the
     // previous layer. Repeat this until the most recent layer is all zero, which means the pattern
     // has been found.
+    //
+    // If only the final, empty layer is "all zero", the reading never actually reduced: any n
+    // values fit a polynomial of degree n - 1, so there's no evidence for the extrapolation.
+    // Alongside the extrapolation, return whether the reading did reduce to zero.
 
+    let length = reading.len();
     let mut extrapolations = vec![reading];
 
     while extrapolations
@@ -32,30 +37,246 @@ fn unfurl_reading(reading: Vec<isize>, direction: &Direction) -> isize {
         extrapolations.push(new_extrapolation);
     }
 
+    let reduced = extrapolations.len() <= length;
+
+    // Drop a trailing empty layer, it has nothing to add.
+    extrapolations.retain(|layer| !layer.is_empty());
+
     // For looking at the history, calculate from the deepest layer upwards, subtracting the
     // accumulation from the next layer up.
-    return match direction {
+    let extrapolation = match direction {
         Direction::Future => extrapolations.iter().fold(0, |acc, x| acc + x.iter().last().unwrap()),
         Direction::History => extrapolations.iter().rev().fold(0, |acc, x| x.iter().nth(0).unwrap() - acc)
     };
+
+    return (extrapolation, reduced);
 }
 
-fn oasis_scan(direction: Direction) -> isize {
-    // For each line, take it as a list of integers and calculate the next integer in the pattern.
-    // This can be applied forwards and backwards.
+fn least_squares(reading: &[isize], degree: usize) -> (Vec<f64>, f64) {
+    // Fit a polynomial of the given degree by least squares, solving the normal equations with
+    // Gaussian elimination. Returns the coefficients, lowest power first, and the root mean
+    // square error of the fit.
+
+    let size = degree.min(reading.len().saturating_sub(1)) + 1;
+    let mut system = vec![vec![0.0; size + 1]; size];
+
+    reading.iter().enumerate().for_each(|(x, y)| {
+        let powers = (0..size).map(|p| (x as f64).powi(p as i32)).collect::<Vec<f64>>();
+
+        for row in 0..size {
+            for col in 0..size {
+                system[row][col] += powers[row] * powers[col];
+            }
+
+            system[row][size] += powers[row] * *y as f64;
+        }
+    });
+
+    for col in 0..size {
+        // Partial pivoting, for stability.
+        let pivot = (col..size)
+            .max_by(|a, b| system[*a][col].abs().total_cmp(&system[*b][col].abs()))
+            .unwrap();
+
+        system.swap(col, pivot);
+
+        for row in 0..size {
+            if row != col && system[col][col] != 0.0 {
+                let factor = system[row][col] / system[col][col];
+                let pivot_row = system[col].clone();
+
+                system[row].iter_mut().zip(pivot_row).for_each(|(v, p)| *v -= factor * p);
+            }
+        }
+    }
+
+    let coefficients = (0..size)
+        .map(|row| if system[row][row] == 0.0 { 0.0 } else { system[row][size] / system[row][row] })
+        .collect::<Vec<f64>>();
+
+    let squared_error = reading
+        .iter()
+        .enumerate()
+        .map(|(x, y)| (evaluate(&coefficients, x as f64) - *y as f64).powi(2))
+        .sum::<f64>();
+
+    return (coefficients, (squared_error / reading.len().max(1) as f64).sqrt());
+}
+
+fn evaluate(coefficients: &[f64], x: f64) -> f64 {
+    return coefficients.iter().rev().fold(0.0, |acc, c| acc * x + c);
+}
+
+fn gcd(a: i128, b: i128) -> i128 {
+    // Always positive, as long as `b` isn't zero.
+
+    if b == 0 {
+        return a.abs();
+    }
+
+    return gcd(b, a % b);
+}
+
+struct Polynomial {
+    // The first entry of each difference layer, up to the last non-zero layer. In Newton's
+    // forward difference form, `f(x) = sum over k of differences[k] * (x choose k)`.
+    differences: Vec<i128>
+}
+
+impl Polynomial {
+    fn fit(reading: &[isize]) -> Option<Polynomial> {
+        // Build the difference layers as `unfurl_reading` does, keeping only their first entries.
+        // `None` if the differences overflow.
+
+        let mut layer = reading.iter().map(|v| *v as i128).collect::<Vec<i128>>();
+        let mut differences = vec![];
+
+        while layer.iter().any(|v| *v != 0) {
+            differences.push(layer[0]);
+
+            layer = layer
+                .windows(2)
+                .map(|slice| slice[1].checked_sub(slice[0]))
+                .collect::<Option<Vec<i128>>>()?;
+        }
+
+        return Some(Polynomial { differences });
+    }
+
+    fn degree(&self) -> usize {
+        // An all-zero reading is counted as a constant.
+
+        return self.differences.len().saturating_sub(1);
+    }
+
+    fn at(&self, x: i128) -> Option<i128> {
+        // Evaluate at any index, where the reading's first value is at 0.
+        //
+        // `x choose k` is built up one factor at a time: `(x choose k - 1) * (x - k + 1)` is
+        // always divisible by `k`, so every step stays an exact integer. Negative `x` works too.
+        // Dividing out `k` before multiplying keeps the intermediate values no bigger than the
+        // result. `None` if the result doesn't fit in an i128.
+
+        let mut choose: i128 = 1;
+        let mut total: i128 = 0;
+
+        for (k, difference) in self.differences.iter().enumerate() {
+            if k > 0 {
+                let k = k as i128;
+                let factor = x.checked_sub(k - 1)?;
+
+                // Whatever part of `k` doesn't divide `choose` must divide `factor`.
+                let shared = gcd(choose, k);
+                choose = (choose / shared).checked_mul(factor / (k / shared))?;
+            }
+
+            total = total.checked_add(difference.checked_mul(choose)?)?;
+        }
+
+        return Some(total);
+    }
+}
+
+fn extrapolate(reading: &[isize], steps: i128, direction: &Direction) -> Option<i128> {
+    // Predict `steps` values past the end of the reading, or before its start.
 
+    let polynomial = Polynomial::fit(reading)?;
+
+    return match direction {
+        Direction::Future => polynomial.at((reading.len() as i128 - 1).checked_add(steps)?),
+        Direction::History => polynomial.at(steps.checked_neg()?)
+    };
+}
+
+fn get_readings() -> Vec<Vec<isize>> {
     if let Some(readings) = fs::read_to_string("data/9.input").ok() {
-        let readings: Vec<Vec<isize>> = readings.lines().map(|line| {
+        return readings.lines().map(|line| {
             line.split_whitespace().filter_map(|v| v.parse::<isize>().ok()).collect()
         }).collect();
-
-        return readings.into_iter().map(|reading| unfurl_reading(reading, &direction)).sum();
     }
 
     panic!("file not found")
 }
 
+fn describe_readings(query: impl Fn(&[isize]) -> Option<i128>) {
+    // Print each reading's degree alongside the queried value.
+
+    get_readings().iter().enumerate().for_each(|(idx, reading)| {
+        let degree = Polynomial::fit(reading)
+            .map(|polynomial| polynomial.degree().to_string())
+            .unwrap_or("?".to_string());
+
+        let warning = if !unfurl_reading(reading.clone(), &Direction::Future).1 {
+            " (doesn't reduce to zero)"
+        } else {
+            ""
+        };
+
+        match query(reading) {
+            Some(value) => println!("reading {}: degree {degree}{warning}, {value}", idx + 1),
+            None => println!("reading {}: degree {degree}{warning}, overflowed", idx + 1)
+        }
+    });
+}
+
+fn oasis_scan(direction: Direction, fallback: Option<usize>) -> isize {
+    // For each line, take it as a list of integers and calculate the next integer in the pattern.
+    // This can be applied forwards and backwards.
+    //
+    // Readings which don't reduce to zero are reported. With a fallback degree, they're instead
+    // extrapolated with a least squares fit of that degree.
+
+    return get_readings().into_iter().enumerate().map(|(idx, reading)| {
+        let (extrapolation, reduced) = unfurl_reading(reading.clone(), &direction);
+
+        if reduced {
+            return extrapolation;
+        }
+
+        let line = reading.iter().map(|v| v.to_string()).collect::<Vec<String>>().join(" ");
+        eprintln!("warning: line {} doesn't reduce to zero: {line}", idx + 1);
+
+        if let Some(degree) = fallback {
+            let (coefficients, error) = least_squares(&reading, degree);
+            let x = match direction {
+                Direction::Future => reading.len() as f64,
+                Direction::History => -1.0
+            };
+            let fitted = evaluate(&coefficients, x).round() as isize;
+
+            eprintln!("    degree {degree} least squares fit gives {fitted} (rms error {error:.3})");
+
+            return fitted;
+        }
+
+        return extrapolation;
+    }).sum();
+}
+
 fn main() {
-    println!("part one: {}", oasis_scan(Direction::Future));
-    println!("part two: {}", oasis_scan(Direction::History));
+    // `cargo run --bin day9 -- future|history <steps>` extrapolates several steps out,
+    // `cargo run --bin day9 -- at <index>` evaluates each reading's polynomial at any index,
+    // `cargo run --bin day9 -- fit <degree>` falls back to least squares for unreduced readings.
+
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+    let number = |arg: &str| match arg.parse::<i128>() {
+        Ok(number) => number,
+        Err(_) => panic!("improper number: {arg}")
+    };
+
+    match args.iter().map(|arg| arg.as_str()).collect::<Vec<&str>>()[..] {
+        ["future", steps] => describe_readings(|reading| extrapolate(reading, number(steps), &Direction::Future)),
+        ["history", steps] => describe_readings(|reading| extrapolate(reading, number(steps), &Direction::History)),
+        ["at", index] => describe_readings(|reading| Polynomial::fit(reading)?.at(number(index))),
+        ["fit", degree] => {
+            let degree = number(degree) as usize;
+
+            println!("part one: {}", oasis_scan(Direction::Future, Some(degree)));
+            println!("part two: {}", oasis_scan(Direction::History, Some(degree)));
+        },
+        _ => {
+            println!("part one: {}", oasis_scan(Direction::Future, None));
+            println!("part two: {}", oasis_scan(Direction::History, None));
+        }
+    }
 }